@@ -1,5 +1,5 @@
 use self::subcommands::Subcommands;
-use crate::git::{Git, GitResult, DRY_RUN, PRINT_COMMANDS};
+use crate::git::{Git, GitResult, DRY_RUN, NO_PAGER, PRINT_COMMANDS};
 use clap::{
     builder::{styling::AnsiColor, Styles},
     error::ErrorKind,
@@ -59,6 +59,10 @@ pub struct CliOptions {
     /// Print the `std::process::Command`s that will be executed, but do not run
     #[arg(long, short = 'd')]
     pub dry_run: bool,
+
+    /// Write output straight to stdout instead of through the pager, for git-util's own output and the Git commands it runs
+    #[arg(long, short = 'P')]
+    pub no_pager: bool,
 }
 
 #[derive(Args, Debug, Clone, Copy)]
@@ -77,6 +81,12 @@ impl Cli {
         // global flags
         PRINT_COMMANDS.store(self.options.print_command, Ordering::Relaxed);
         DRY_RUN.store(self.options.dry_run, Ordering::Relaxed);
+        NO_PAGER.store(self.options.no_pager, Ordering::Relaxed);
+
+        if self.options.no_pager {
+            // the Git commands that are run inherit this, as they would `git --no-pager`
+            std::env::set_var("GIT_PAGER", "cat");
+        }
 
         if let Some(args) = &self.fallback {
             Git::pass_through(args)
@@ -1,15 +1,205 @@
 use super::GitConfigOpts;
-use crate::git::{
-    commands::{immutable::ImmutableCommands, mutable},
-    hooks::pre_commit::PreCommitHook,
-    GitCommandResult, GitResult,
+use crate::{
+    git::{
+        commands::{immutable::ImmutableCommands, mutable},
+        history::{
+            filter::{self, LogFilter},
+            RecordFormat,
+        },
+        hooks::{
+            commit_checks, commit_msg::CommitMsgHook, install, pre_commit::PreCommitHook,
+            pre_push::PrePushHook, prepare_commit_msg::PrepareCommitMsgHook, report::ReportTarget,
+            Hook,
+        },
+        journal::Snapshot,
+        GitCommandResult, GitResult,
+    },
+    print::Print,
 };
-use clap::Subcommand;
+use clap::{Args, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
-#[derive(Subcommand, Debug, Clone, Copy)]
+#[derive(Subcommand, Debug, Clone)]
 pub enum HookSubcommands {
+    /// Install shims that call git-util for the specified hooks; existing hooks are preserved and chained.
+    Install {
+        /// The hooks to install
+        #[arg(required_unless_present = "all")]
+        hooks: Vec<Hook>,
+
+        /// Install all hooks
+        #[arg(long, conflicts_with = "hooks")]
+        all: bool,
+
+        /// Install into a directory managed by git-util and set it as the global `core.hooksPath`
+        #[arg(long)]
+        global: bool,
+    },
+    /// Remove the shims installed by `hook install`, restoring any hooks they chained.
+    Uninstall {
+        /// The hooks to uninstall
+        #[arg(required_unless_present = "all")]
+        hooks: Vec<Hook>,
+
+        /// Uninstall all hooks
+        #[arg(long, conflicts_with = "hooks")]
+        all: bool,
+
+        /// Uninstall from the global hooks directory
+        #[arg(long)]
+        global: bool,
+    },
+    /// Show which hooks are installed locally and globally.
+    Status {},
+    /// Run the author and content checks against existing commits, e.g. to audit a branch before enabling the hooks.
+    Check {
+        /// The commits to check, e.g. `main..feature`
+        #[arg(long, required_unless_present = "all_history")]
+        range: Option<String>,
+
+        /// Check every commit reachable from any ref
+        #[arg(long, conflicts_with = "range")]
+        all_history: bool,
+
+        #[clap(flatten)]
+        report: ReportOpts,
+    },
+    /// `commit-msg` hook
+    CommitMsg {
+        /// The file containing the proposed commit message (passed by Git)
+        file: PathBuf,
+
+        #[clap(flatten)]
+        report: ReportOpts,
+    },
     /// `pre-commit` hook
-    PreCommit {},
+    PreCommit {
+        #[clap(flatten)]
+        report: ReportOpts,
+    },
+    /// `pre-push` hook; the refs being pushed are read from stdin
+    PrePush {
+        /// The name of the remote being pushed to (passed by Git)
+        remote: String,
+        /// The URL of the remote being pushed to (passed by Git)
+        url: Option<String>,
+
+        #[clap(flatten)]
+        report: ReportOpts,
+    },
+    /// `prepare-commit-msg` hook
+    PrepareCommitMsg {
+        /// The file containing the commit message (passed by Git)
+        file: PathBuf,
+        /// The source of the commit message: `message`, `template`, `merge`, `squash` or `commit` (passed by Git)
+        source: Option<String>,
+        /// The commit object name when the source is `commit` (passed by Git)
+        sha: Option<String>,
+
+        #[clap(flatten)]
+        report: ReportOpts,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ReportOpts {
+    /// Also write the findings to PATH as `json` or `sarif`, e.g. `--report sarif hooks.sarif`
+    #[arg(long, num_args = 2, value_names = ["FORMAT", "PATH"])]
+    pub report: Option<Vec<String>>,
+}
+
+/// Filters for the log-style subcommands; added before the command arguments, except the paths, which are added after
+#[derive(Args, Debug, Clone)]
+pub struct LogFilterOpts {
+    /// Only list commits authored with the current `user.email`
+    #[arg(long)]
+    mine: bool,
+
+    /// Only list commits whose author name or email matches PATTERN
+    #[arg(long, value_name = "PATTERN")]
+    author: Option<String>,
+
+    /// Only list commits more recent than a date or a duration, e.g. `2024-05-01`, `2w` or `3 days`
+    #[arg(long, value_name = "WHEN")]
+    since: Option<String>,
+
+    /// Only list commits older than a date or a duration, e.g. `2024-05-01`, `2w` or `3 days`
+    #[arg(long, value_name = "WHEN")]
+    until: Option<String>,
+
+    /// Only list commits that change PATH (repeatable)
+    #[arg(long = "path", value_name = "PATH")]
+    paths: Vec<String>,
+
+    /// Only list commits whose message matches PATTERN
+    #[arg(long, value_name = "PATTERN")]
+    grep: Option<String>,
+
+    /// Leave out merge commits
+    #[arg(long)]
+    no_merges: bool,
+
+    /// Only list commits since the merge-base with the default branch
+    #[arg(long)]
+    branch_only: bool,
+}
+
+impl From<&LogFilterOpts> for LogFilter {
+    fn from(opts: &LogFilterOpts) -> Self {
+        LogFilter {
+            mine: opts.mine,
+            author: opts.author.clone(),
+            since: opts.since.clone(),
+            until: opts.until.clone(),
+            paths: opts.paths.clone(),
+            grep: opts.grep.clone(),
+            no_merges: opts.no_merges,
+            branch_only: opts.branch_only,
+        }
+    }
+}
+
+/// Options for listing everything on the current branch rather than the last n commits
+#[derive(Args, Debug, Clone)]
+pub struct BaseOpts {
+    /// List every commit since the branch point: the merge-base with REF, else with the upstream or default branch
+    #[arg(long, value_name = "REF", num_args = 0..=1)]
+    base: Option<Option<String>>,
+
+    /// Also include staged and unstaged changes
+    #[arg(long, conflicts_with = "format")]
+    uncommitted: bool,
+}
+
+impl BaseOpts {
+    /// The `BRANCH_POINT..HEAD` range, if `--base` was passed.
+    fn range(&self) -> anyhow::Result<Option<String>> {
+        self.base
+            .as_ref()
+            .map(|base| filter::branch_point(base.as_deref()).map(|point| format!("{point}..HEAD")))
+            .transpose()
+    }
+}
+
+/// The machine-readable formats the log-style subcommands can print commits in
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum FormatName {
+    /// A JSON array of commits
+    Json,
+    /// One row per commit, with the file stats summed
+    Csv,
+    /// One JSON commit per line
+    Ndjson,
+}
+
+impl From<FormatName> for RecordFormat {
+    fn from(format: FormatName) -> Self {
+        match format {
+            FormatName::Json => RecordFormat::Json,
+            FormatName::Csv => RecordFormat::Csv,
+            FormatName::Ndjson => RecordFormat::Ndjson,
+        }
+    }
 }
 
 /// Specify which files to operate a command against
@@ -84,6 +274,19 @@ pub enum Subcommands {
     Author {
         /// Number of commits to reset (else defaults to 1)
         num: Option<u16>,
+
+        /// Rewrite the commits even if they have been pushed, or include a merge or the root commit
+        #[arg(long)]
+        force: bool,
+    },
+    /// Generate a Markdown changelog from the Conventional Commits since the latest tag.
+    Changelog {
+        /// The commits to include, as `FROM..TO` or `FROM` (for `FROM..HEAD`); else since the latest tag
+        range: Option<String>,
+
+        /// A template file, overriding `git-util.changelog.template`
+        #[arg(long, value_name = "FILE")]
+        template: Option<String>,
     },
     /// Commit with message (alias for `git commit -m`).
     ///
@@ -108,11 +311,27 @@ pub enum Subcommands {
         #[command(subcommand)]
         hook: HookSubcommands,
     },
-    /// List the files that changed in the last n commits.
+    /// List the files that changed in the last n commits, once each with their net status.
     #[clap(alias = "shf")]
     Files {
-        /// The number of commits to list files for (else defaults to 1)
-        num: Option<u16>,
+        /// The number of commits to list files for (else defaults to 1), or a revision range, e.g. `main..HEAD`
+        #[arg(conflicts_with = "base")]
+        commits: Option<String>,
+
+        #[clap(flatten)]
+        since: BaseOpts,
+
+        /// Group the files under their directories
+        #[arg(long)]
+        tree: bool,
+
+        /// Only list files whose path matches GLOB, e.g. `src/**/*.rs` (repeatable)
+        #[arg(long = "glob", value_name = "GLOB")]
+        globs: Vec<String>,
+
+        /// Print the commits as records with hashes, parents, identities, dates, message, trailers and file stats
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<FormatName>,
     },
     /// Wrapper around `git-log`, formatted to 1 line per commit.
     #[command(allow_hyphen_values = true)]
@@ -120,17 +339,43 @@ pub enum Subcommands {
         /// The number of commits to list (else defaults to 25)
         num: Option<u16>,
 
+        /// Print the commits as records with hashes, parents, identities, dates, message, trailers and file stats
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<FormatName>,
+
+        /// Draw the commit graph to the left of the log, collapsing long runs of linear history
+        #[arg(long, conflicts_with = "format")]
+        graph: bool,
+
+        /// Also list the upstream's commits that haven't been pulled yet
+        #[arg(long)]
+        behind: bool,
+
+        #[clap(flatten)]
+        filter: LogFilterOpts,
+
         /// Command arguments
         args: Vec<String>,
     },
     /// List commit message and of changed files for the last n commits; wrapper around `git-log --compact-summary`.
     #[clap(alias = "la")]
-    #[command(allow_hyphen_values = true)]
     Last {
         /// The number of commits to list (else defaults to 10)
+        #[arg(conflicts_with = "base", allow_hyphen_values = true)]
         num: Option<u16>,
 
+        /// Print the commits as records with hashes, parents, identities, dates, message, trailers and file stats
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<FormatName>,
+
+        #[clap(flatten)]
+        filter: LogFilterOpts,
+
+        #[clap(flatten)]
+        since: BaseOpts,
+
         /// Command arguments
+        #[arg(allow_hyphen_values = true)]
         args: Vec<String>,
     },
     /// Wrapper around `git-restore`.
@@ -144,6 +389,15 @@ pub enum Subcommands {
         /// Command arguments
         args: Vec<String>,
     },
+    /// List the undo journal, or restore the state from before one of the subcommands in it.
+    Oops {
+        /// List the journal (the default)
+        #[arg(long, conflicts_with = "entry")]
+        list: bool,
+
+        /// The journal entry to restore; 1 is the most recent
+        entry: Option<usize>,
+    },
     /// Interactively rebase the last n commits
     #[command(allow_hyphen_values = true)]
     Ri {
@@ -160,10 +414,30 @@ pub enum Subcommands {
         /// Command arguments
         args: Vec<String>,
     },
+    /// Summarize the lines changed per file, directory and author across commits.
+    Stats {
+        /// The number of commits to summarize (else defaults to 25), or a revision range, e.g. `v1.0..HEAD`
+        commits: Option<String>,
+
+        /// Print the stats as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// The number of rows in each table
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        top: usize,
+
+        #[clap(flatten)]
+        filter: LogFilterOpts,
+    },
     /// Reset the last n commits and keep the undone changes in working directory.
     Undo {
         /// The number of commits to undo (else defaults to 1)
         num: Option<u16>,
+
+        /// Undo the commits even if they have been pushed, or include a merge or the root commit
+        #[arg(long)]
+        force: bool,
     },
     /// Move staged files back to staging area; wrapper around `git-restore --staged`.
     #[clap(alias = "u")]
@@ -187,6 +461,19 @@ pub enum Subcommands {
 
 impl Subcommands {
     pub fn run(&self) -> Result<GitCommandResult, anyhow::Error> {
+        let command = std::env::args().skip(1).collect::<Vec<String>>().join(" ");
+
+        // a snapshot that can't be taken mustn't stop the subcommand, e.g. `add` during a merge conflict
+        let snapshot = match self {
+            Subcommands::Update { branch } => Some(Snapshot::of_branch(&command, branch)),
+            Subcommands::Oops { .. } => None,
+            _ if self.is_mutating() => Some(Snapshot::of_head(&command)),
+            _ => None,
+        };
+        if let Some(Err(e)) = snapshot.map(|snapshot| snapshot.and_then(|s| s.record())) {
+            Print::warning(&format!("Failed to record an undo snapshot: {e:#}"));
+        }
+
         match self {
             Subcommands::Add { which, args } => {
                 if let Some(which) = which {
@@ -212,7 +499,10 @@ impl Subcommands {
             Subcommands::Auf {} => mutable::add::updated_forced(),
             Subcommands::Auc {} => mutable::commit::updated(),
             Subcommands::Aumend {} => mutable::commit::amend_updated(),
-            Subcommands::Author { num } => mutable::commit::change_author(*num),
+            Subcommands::Author { num, force } => mutable::commit::change_author(*num, *force),
+            Subcommands::Changelog { range, template } => {
+                ImmutableCommands::changelog(range.as_deref(), template.as_deref())
+            }
             Subcommands::Cm { message, args } => mutable::commit::with_message(
                 message,
                 match args {
@@ -230,10 +520,53 @@ impl Subcommands {
                 )
             }
             Subcommands::Hook { hook } => hook.run(),
-            Subcommands::Files { num } => ImmutableCommands::show_files(*num),
-            Subcommands::L { num, args } => ImmutableCommands::one_line_log(*num, args),
-            Subcommands::Last { num, args } => ImmutableCommands::compact_summary_log(*num, args),
+            Subcommands::Files {
+                commits,
+                since,
+                tree,
+                globs,
+                format,
+            } => ImmutableCommands::show_files(
+                since.range()?.as_deref().or(commits.as_deref()),
+                format.map(Into::into),
+                *tree,
+                globs,
+                since.uncommitted,
+            ),
+            Subcommands::L {
+                num,
+                format,
+                graph,
+                behind,
+                filter,
+                args,
+            } => ImmutableCommands::one_line_log(
+                *num,
+                &LogFilter::from(filter).apply(args)?,
+                format.map(Into::into),
+                *graph,
+                *behind,
+            ),
+            Subcommands::Last {
+                num,
+                format,
+                filter,
+                since,
+                args,
+            } => ImmutableCommands::compact_summary_log(
+                *num,
+                &LogFilter::from(filter).apply(args)?,
+                format.map(Into::into),
+                since.range()?,
+                since.uncommitted,
+            ),
             Subcommands::Show { num, args } => ImmutableCommands::show(*num, args),
+            Subcommands::Stats {
+                commits,
+                json,
+                top,
+                filter,
+            } => ImmutableCommands::stats(commits.as_deref(), &filter.into(), *json, *top),
             Subcommands::Restore { which, args } => {
                 if let Some(all) = which {
                     match all {
@@ -243,8 +576,12 @@ impl Subcommands {
                     mutable::index::restore(args)
                 }
             }
+            Subcommands::Oops { list: _, entry } => match entry {
+                Some(entry) => mutable::oops::restore(*entry, &command),
+                None => mutable::oops::list(),
+            },
             Subcommands::Ri { num } => mutable::commit::interactive_rebase(*num),
-            Subcommands::Undo { num } => mutable::commit::undo(*num),
+            Subcommands::Undo { num, force } => mutable::commit::undo(*num, *force),
             Subcommands::Unstage { which, args } => {
                 if let Some(which) = which {
                     match which {
@@ -257,12 +594,97 @@ impl Subcommands {
             Subcommands::Update { branch } => mutable::update_branch_from_remote(branch),
         }
     }
+
+    /// Whether the subcommand can change refs, the index or the working tree, and so is recorded in the undo journal.
+    fn is_mutating(&self) -> bool {
+        match self {
+            Subcommands::Add { .. }
+            | Subcommands::Aa {}
+            | Subcommands::Aac {}
+            | Subcommands::Aaf {}
+            | Subcommands::Aamend {}
+            | Subcommands::Au {}
+            | Subcommands::Auc {}
+            | Subcommands::Auf {}
+            | Subcommands::Aumend {}
+            | Subcommands::Author { .. }
+            | Subcommands::Cm { .. }
+            | Subcommands::Oops { .. }
+            | Subcommands::Restore { .. }
+            | Subcommands::Ri { .. }
+            | Subcommands::Undo { .. }
+            | Subcommands::Unstage { .. }
+            | Subcommands::Update { .. } => true,
+            Subcommands::Alias { .. }
+            | Subcommands::Changelog { .. }
+            | Subcommands::Conf { .. }
+            | Subcommands::Files { .. }
+            | Subcommands::Hook { .. }
+            | Subcommands::L { .. }
+            | Subcommands::Last { .. }
+            | Subcommands::Show { .. }
+            | Subcommands::Stats { .. } => false,
+        }
+    }
+}
+
+impl ReportOpts {
+    /// Set where the hook writes its report, if `--report` was passed.
+    fn set_target(&self) -> anyhow::Result<()> {
+        match &self.report {
+            Some(values) => ReportTarget::parse(values)?.set(),
+            None => Ok(()),
+        }
+    }
 }
 
 impl HookSubcommands {
     fn run(&self) -> GitResult {
         match self {
-            HookSubcommands::PreCommit {} => PreCommitHook::run(),
+            HookSubcommands::Install { hooks, all, global } => {
+                install::install(&Self::selected_hooks(hooks, *all), *global)
+            }
+            HookSubcommands::Uninstall { hooks, all, global } => {
+                install::uninstall(&Self::selected_hooks(hooks, *all), *global)
+            }
+            HookSubcommands::Status {} => install::status(),
+            HookSubcommands::Check { range, report, .. } => {
+                report.set_target()?;
+                commit_checks::check_history(range.as_deref())
+            }
+            HookSubcommands::CommitMsg { file, report } => {
+                report.set_target()?;
+                CommitMsgHook::run(file)
+            }
+            HookSubcommands::PreCommit { report } => {
+                report.set_target()?;
+                PreCommitHook::run()
+            }
+            HookSubcommands::PrePush {
+                remote,
+                url,
+                report,
+            } => {
+                report.set_target()?;
+                PrePushHook::run(remote, url.as_deref())
+            }
+            HookSubcommands::PrepareCommitMsg {
+                file,
+                source,
+                sha,
+                report,
+            } => {
+                report.set_target()?;
+                PrepareCommitMsgHook::run(file, source.as_deref(), sha.as_deref())
+            }
+        }
+    }
+
+    fn selected_hooks(hooks: &[Hook], all: bool) -> Vec<Hook> {
+        if all {
+            Hook::ALL.to_vec()
+        } else {
+            hooks.to_vec()
         }
     }
 }
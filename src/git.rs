@@ -1,16 +1,22 @@
-use anyhow::{Context, Ok, Result};
+use anyhow::{anyhow, Context, Ok, Result};
 use log::{debug, trace};
 use std::{
-    io::{stdout, IsTerminal},
-    process::Command,
+    io::{stdout, IsTerminal, Write},
+    process::{Child, Command, Output, Stdio},
     sync::atomic::AtomicBool,
 };
 
 use crate::{commands::Commands, print::Print};
 
 pub mod commands;
+pub mod config;
+pub mod diff;
 pub mod env_vars;
+pub mod history;
 pub mod hooks;
+pub mod journal;
+pub mod message;
+pub mod pager;
 
 pub type GitResult = Result<GitCommandResult>;
 pub struct Git();
@@ -21,6 +27,9 @@ pub static PRINT_COMMANDS: AtomicBool = AtomicBool::new(false);
 /// Flag used to indicate whether subcommand is a dry run
 pub static DRY_RUN: AtomicBool = AtomicBool::new(false);
 
+/// Flag used to indicate whether output should be written straight to `stdout` instead of through the pager
+pub static NO_PAGER: AtomicBool = AtomicBool::new(false);
+
 /// Represents a call to the Git CLI in the form: `git SUBCOMMAND [DEFAULT_ARGS] [USER_ARGS]`
 #[derive(Debug, PartialEq, Eq)]
 pub struct GitCommand<'a> {
@@ -57,6 +66,57 @@ impl Git {
         command.run()
     }
 
+    /// The short name of the currently checked out branch, or `None` if `HEAD` is detached.
+    pub fn current_branch() -> Result<Option<String>> {
+        let output = GitCommand::new("symbolic-ref")
+            .with_default_args(&["--short", "--quiet", "HEAD"])
+            .capture()?;
+
+        if output.status.success() {
+            Ok(Some(String::from_utf8(output.stdout)?.trim().to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The full name of the current branch's upstream (e.g. `refs/remotes/origin/main`), or `None` if it has none or
+    /// `HEAD` is detached.
+    ///
+    /// `git rev-parse --verify --quiet --symbolic-full-name @{upstream}`
+    pub fn upstream() -> Result<Option<String>> {
+        let output = GitCommand::new("rev-parse")
+            .with_default_args(&["--verify", "--quiet", "--symbolic-full-name", "@{upstream}"])
+            .capture()?;
+
+        Ok(output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|upstream| !upstream.is_empty()))
+    }
+
+    /// The short name of a branch or remote-tracking branch, e.g. `origin/main` for `refs/remotes/origin/main`.
+    pub fn short_name(reference: &str) -> &str {
+        reference
+            .strip_prefix("refs/heads/")
+            .or_else(|| reference.strip_prefix("refs/remotes/"))
+            .unwrap_or(reference)
+    }
+
+    /// The hash of the commit `revision` names, or `None` if it doesn't name one (e.g. `HEAD` on an unborn branch).
+    ///
+    /// `git rev-parse --verify --quiet REVISION^{commit}`
+    pub fn rev_parse(revision: &str) -> Result<Option<String>> {
+        let output = GitCommand::new("rev-parse")
+            .with_default_args(&["--verify", "--quiet", &format!("{revision}^{{commit}}")])
+            .capture()?;
+
+        Ok(output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+    }
+
     fn parse_config_options(options: GitConfigOpts, config_args: &mut Vec<&str>) {
         if options.show_origin {
             config_args.push("--show-origin")
@@ -196,28 +256,97 @@ impl GitCommand<'_> {
         }
     }
 
+    /// Execute the **Git Subcommand** represented by `self` without color and capture its output.
+    ///
+    /// Unlike `run`, this is executed even if `DRY_RUN` is set, since it is used to read repository state.
+    fn capture(&self) -> Result<Output> {
+        trace!("capture() called with: {:#?}", self);
+
+        Commands::new_command_with_args("git", &self.parse_command_args(false))
+            .output()
+            .with_context(|| format!("Failed to execute 'git {}' command", self.subcommand))
+    }
+
+    /// Spawn the **Git Subcommand** represented by `self` without color, with `stdout` piped so it can be streamed.
+    fn spawn_with_piped_stdout(&self) -> Result<Child> {
+        trace!("spawn_with_piped_stdout() called with: {:#?}", self);
+
+        Commands::new_command_with_args("git", &self.parse_command_args(false))
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to execute 'git {}' command", self.subcommand))
+    }
+
+    /// Same as `capture`, but write `input` to the command's stdin.
+    fn capture_with_stdin(&self, input: &[u8]) -> Result<Output> {
+        trace!("capture_with_stdin() called with: {:#?}", self);
+
+        let mut child = Commands::new_command_with_args("git", &self.parse_command_args(false))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to execute 'git {}' command", self.subcommand))?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .with_context(|| format!("Failed to open stdin of 'git {}'", self.subcommand))?;
+        let input = input.to_vec();
+
+        // write from another thread so that a full stdout pipe can't deadlock the write
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("Failed to get 'git {}' output", self.subcommand))?;
+
+        writer
+            .join()
+            .map_err(|_| anyhow!("Failed to write to stdin of 'git {}'", self.subcommand))??;
+
+        Ok(output)
+    }
+
+    /// Same as `capture`, but return `stdout` as a `String`; fails if `git` exits with an error.
+    ///
+    /// Invalid UTF-8 (e.g. in a diff of a Latin-1 file) is replaced rather than treated as an error.
+    fn read_stdout(&self) -> Result<String> {
+        let output = self.capture()?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        } else {
+            Err(anyhow!(
+                "'git {}' failed: {}",
+                self.subcommand,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+
     /// Construct a `std::process:Command` that calls `git` using the **Git Subcommand** represented by `self`.
     fn construct_git_command_string(&self) -> String {
         trace!("construct_git_command() called with: {:#?}", self);
 
-        let command_args = self.parse_command_args();
+        let command_args = self.parse_command_args(false);
 
-        format!("git {} {}", self.subcommand, command_args.join(" "))
+        format!("git {}", command_args.join(" "))
     }
 
     /// Construct a `std::process:Command` that calls `git` using the **Git Subcommand** represented by `self`.
     fn construct_git_command(&self) -> Command {
         trace!("construct_git_command() called with: {:#?}", self);
 
-        let command_args = self.parse_command_args();
+        let command_args = self.parse_command_args(stdout().is_terminal());
 
         Commands::new_command_with_args("git", &command_args)
     }
 
-    fn parse_command_args(&self) -> Vec<&str> {
+    fn parse_command_args(&self, colored: bool) -> Vec<&str> {
         trace!("parse_command_args() called with: {:#?}", self);
 
-        let mut command_args: Vec<&str> = if stdout().is_terminal() {
+        let mut command_args: Vec<&str> = if colored {
             vec!["-c", "color.ui=always", self.subcommand]
         } else {
             vec![self.subcommand]
@@ -1,13 +1,27 @@
 use crate::git::{GitCommandResult, GitResult};
 use crate::{
     commands::ripgrep::{Ripgrep, RipgrepOptions},
-    git::{Git, GitCommand},
+    git::{
+        history::{
+            self,
+            changelog::{Changelog, ChangelogFormat},
+            files::{self, ChangedPath},
+            filter::LogFilter,
+            graph::Highlights,
+            stats::ChurnStats,
+            tracking::Tracking,
+            Commit, CommitRecord, RecordFormat,
+        },
+        pager, Git, GitCommand,
+    },
+    glob::Glob,
+    print::Print,
 };
 use crate::{commands::Commands, git::GitConfigOpts};
-use anyhow::Context;
+use anyhow::anyhow;
 use log::trace;
 use std::{
-    io::{self, Write},
+    io::{self, IsTerminal},
     process::{ChildStdout, Output},
 };
 
@@ -15,17 +29,61 @@ use std::{
 pub struct ImmutableCommands();
 
 impl ImmutableCommands {
-    /// `git log --compact-summary --max-count=NUM ARGS`
-    pub fn compact_summary_log(num: Option<u16>, args: &[String]) -> GitResult {
+    /// Render the Conventional Commits in `range` (by default, since the latest tag) as a Markdown changelog, with the
+    /// `template` file if one is given.
+    pub fn changelog(range: Option<&str>, template: Option<&str>) -> GitResult {
+        trace!("changelog() called with: {:#?}, {:#?}", range, template);
+
+        let format = ChangelogFormat::from_config(template)?;
+        let output = Changelog::read(range)?.render(&format);
+
+        pager::page("changelog", output.as_bytes())?;
+
+        Ok(GitCommandResult::Success)
+    }
+
+    /// `git log --compact-summary --max-count=NUM ARGS`, or the commit records in `format`.
+    ///
+    /// With `since` (a revision range such as `BASE..HEAD`), every commit in it is listed instead of the last NUM; with
+    /// `uncommitted`, `git diff --compact-summary HEAD` follows the log.
+    pub fn compact_summary_log(
+        num: Option<u16>,
+        args: &[String],
+        format: Option<RecordFormat>,
+        since: Option<String>,
+        uncommitted: bool,
+    ) -> GitResult {
         trace!("last() called with: {:#?}, {:#?}", num, args);
 
-        GitCommand::new("log")
-            .with_default_args(&[
-                "--compact-summary",
-                &format!("--max-count={}", num.unwrap_or(1)),
-            ])
-            .with_user_args(args)
-            .run()
+        let max_count = match since {
+            Some(_) => None,
+            None => Some(format!("--max-count={}", num.unwrap_or(1))),
+        };
+        let options: Vec<&str> = max_count.iter().map(String::as_str).collect();
+        let args: Vec<String> = since.into_iter().chain(args.iter().cloned()).collect();
+
+        if let Some(format) = format {
+            return Self::print_records("last", &options, &args, format);
+        }
+
+        let default_args: Vec<&str> = ["--compact-summary"]
+            .iter()
+            .chain(&options)
+            .copied()
+            .collect();
+        let result = GitCommand::new("log")
+            .with_default_args(&default_args)
+            .with_user_args(&args)
+            .run()?;
+
+        if uncommitted && result == GitCommandResult::Success {
+            println!();
+            GitCommand::new("diff")
+                .with_default_args(&["--compact-summary", "HEAD"])
+                .run()
+        } else {
+            Ok(result)
+        }
     }
 
     /// List configured aliases, optionally filtering on those containing `filter`.
@@ -60,9 +118,7 @@ impl ImmutableCommands {
 
         let aliases_table: Output = Commands::pipe_to_column(delimited_aliases, '\t')?;
 
-        io::stdout()
-            .write_all(&aliases_table.stdout)
-            .with_context(|| "Failed to write column output to stdout")?;
+        pager::page("alias", &aliases_table.stdout)?;
 
         Ok(GitCommandResult::Success)
     }
@@ -97,50 +153,77 @@ impl ImmutableCommands {
 
         let config_table: Output = Commands::pipe_to_column(filtered_configs, '=')?;
 
-        io::stdout()
-            .write_all(&config_table.stdout)
-            .with_context(|| "Failed to write column output to stdout")?;
+        pager::page("conf", &config_table.stdout)?;
 
         Ok(GitCommandResult::Success)
     }
 
-    /// `git log --pretty='%C(yellow)%h %C(magenta)%as %C(blue)%aL %C(cyan)%s%C(reset)' --max-count=NUM ARGS`
-    pub fn one_line_log(num: Option<u16>, args: &[String]) -> GitResult {
+    /// `git log --max-count=NUM ARGS`, rendered as aligned columns: hash, relative date, author, refs and subject; or the
+    /// commit records in `format`. With `graph`, the ancestry is drawn in lanes to the left of the columns.
+    ///
+    /// If the current branch has an upstream, a header summarizes how far ahead and behind it is, and the commits that
+    /// haven't been pushed are marked; with `behind`, the upstream's commits are listed too, and those that haven't been
+    /// pulled are marked as well.
+    pub fn one_line_log(
+        num: Option<u16>,
+        args: &[String],
+        format: Option<RecordFormat>,
+        graph: bool,
+        behind: bool,
+    ) -> GitResult {
         trace!("log_oneline() called with: {:#?}", num);
 
-        let log_output: Output = GitCommand::new("log")
-            .with_default_args(&[
-                "--pretty='%C(yellow)%h %C(magenta)%as %C(blue)%aL %C(cyan)%s%C(reset)'",
-                &format!("--max-count={}", num.unwrap_or(25)),
-            ])
-            .with_user_args(args)
-            .construct_git_command()
-            .output()
-            .with_context(|| "Failed to execute 'git log' command")?;
-
-        if log_output.status.success() {
-            let log_output_string = String::from_utf8(log_output.stdout)?;
-            let log_lines = log_output_string.lines();
-
-            let mut trimmed_log_output: String = String::new();
+        let max_count = format!("--max-count={}", num.unwrap_or(25));
+        let tracking = Tracking::read()?;
 
-            // Since git thinks this is not a tty, it wraps the log lines in single quotes; we remove them here.
-            for line in log_lines.into_iter() {
-                trimmed_log_output.push_str(line.trim_matches('\''));
-                trimmed_log_output.push('\n');
+        let args: Vec<String> = if behind {
+            if tracking.is_none() {
+                return Err(anyhow!(
+                    "--behind needs the current branch to have an upstream"
+                ));
             }
+            ["HEAD", "@{upstream}"]
+                .iter()
+                .map(ToString::to_string)
+                .chain(args.iter().cloned())
+                .collect()
+        } else {
+            args.to_vec()
+        };
 
-            println!("{}", trimmed_log_output.trim_end());
-
-            io::stderr().write_all(&log_output.stderr)?;
+        if let Some(format) = format {
+            return Self::print_records("l", &[&max_count], &args, format);
+        }
 
-            Ok(GitCommandResult::Success)
+        let colored = io::stdout().is_terminal();
+
+        let output = if graph {
+            // `--parents` rewrites the parents to the nearest listed ancestors when filtering by path
+            let commits = Commit::read(&[&max_count, "--parents", "--date-order"], &args)?;
+            history::graph::render(
+                &commits,
+                &Highlights::read(),
+                tracking.as_ref(),
+                colored,
+                Print::terminal_width(),
+            )
         } else {
-            io::stdout().write_all(&log_output.stdout)?;
-            io::stderr().write_all(&log_output.stderr)?;
+            let commits = Commit::read(&[&max_count], &args)?;
+            history::render_one_line(
+                &commits,
+                tracking.as_ref(),
+                colored,
+                Print::terminal_width(),
+            )
+        };
 
-            Ok(GitCommandResult::Error)
-        }
+        let header = match &tracking {
+            Some(tracking) => format!("{}\n", tracking.header(colored)),
+            None => String::new(),
+        };
+        pager::page("l", format!("{header}{output}").as_bytes())?;
+
+        Ok(GitCommandResult::Success)
     }
 
     /// `git show --expand-tabs=4 --max-count=NUM ARGS`
@@ -156,17 +239,84 @@ impl ImmutableCommands {
             .run()
     }
 
-    /// `git show --pretty='' --name-only --max-count=NUM`
-    pub fn show_files(num: Option<u16>) -> GitResult {
-        trace!("show_files() called with: {:#?}", num);
+    /// List the paths changed by the last `commits` commits (else 1), or the `commits` revision range, and by the staged
+    /// and unstaged changes if `uncommitted`, once each with their net status and number of commits, optionally filtered
+    /// by `globs` and grouped into a `tree`; or the commit records in `format`.
+    pub fn show_files(
+        commits: Option<&str>,
+        format: Option<RecordFormat>,
+        tree: bool,
+        globs: &[String],
+        uncommitted: bool,
+    ) -> GitResult {
+        trace!("show_files() called with: {:#?}", commits);
+
+        let (max_count, revisions) = Self::count_or_range(commits, 1);
+        let options: Vec<&str> = max_count.iter().map(String::as_str).collect();
+
+        if let Some(format) = format {
+            return Self::print_records("files", &options, &revisions, format);
+        }
 
-        GitCommand::new("show")
-            .with_default_args(&[
-                "--pretty=",
-                "--name-only",
-                &format!("--max-count={}", num.unwrap_or(1)),
-            ])
-            .run()
+        let paths = files::filter(
+            ChangedPath::read(&options, &revisions, uncommitted)?,
+            &Glob::new_all(globs)?,
+        );
+
+        pager::page(
+            "files",
+            files::render(&paths, tree, io::stdout().is_terminal()).as_bytes(),
+        )?;
+
+        Ok(GitCommandResult::Success)
+    }
+
+    /// Print the commits selected by `git log OPTIONS ARGS` as records in `format`, through the pager for `subcommand`.
+    fn print_records(
+        subcommand: &str,
+        options: &[&str],
+        args: &[String],
+        format: RecordFormat,
+    ) -> GitResult {
+        let records = CommitRecord::read(options, args)?;
+
+        pager::page(
+            subcommand,
+            history::render_records(&records, format).as_bytes(),
+        )?;
+
+        Ok(GitCommandResult::Success)
+    }
+
+    /// Summarize the lines changed per file, directory and author in the last `commits` commits (else 25), or in the
+    /// `commits` revision range, as tables of at most `top` rows or as JSON.
+    pub fn stats(commits: Option<&str>, filter: &LogFilter, json: bool, top: usize) -> GitResult {
+        trace!("stats() called with: {:#?}, {:#?}", commits, filter);
+
+        let (max_count, revisions) = Self::count_or_range(commits, 25);
+        let options: Vec<&str> = max_count.iter().map(String::as_str).collect();
+        let records = CommitRecord::read(&options, &filter.apply(&revisions)?)?;
+
+        let stats = ChurnStats::from_records(&records);
+        let output = if json {
+            format!("{}\n", stats.to_json())
+        } else {
+            stats.render_tables(top, io::stdout().is_terminal())
+        };
+
+        pager::page("stats", output.as_bytes())?;
+
+        Ok(GitCommandResult::Success)
+    }
+
+    /// Interpret `commits` as either a number of commits, returning the `--max-count` option, or a revision range,
+    /// returning it as an argument; `None` means the last `default` commits.
+    fn count_or_range(commits: Option<&str>, default: u16) -> (Option<String>, Vec<String>) {
+        match commits.map(|commits| (commits, commits.parse::<u16>())) {
+            None => (Some(format!("--max-count={default}")), Vec::new()),
+            Some((_, Ok(num))) => (Some(format!("--max-count={num}")), Vec::new()),
+            Some((range, Err(_))) => (None, vec![range.to_string()]),
+        }
     }
 
     /// `git status --short`
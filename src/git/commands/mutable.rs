@@ -5,6 +5,7 @@ use log::{debug, trace};
 pub mod add;
 pub mod commit;
 pub mod index;
+pub mod oops;
 
 // `git fetch --verbose origin:BRANCH`
 pub fn update_branch_from_remote(branch: &String) -> GitResult {
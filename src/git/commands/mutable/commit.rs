@@ -1,5 +1,5 @@
 use crate::git::{Git, GitCommand, GitCommandResult, GitResult};
-use anyhow::anyhow;
+use anyhow::{anyhow, Result};
 use log::trace;
 
 /// `git add --all && git commit`
@@ -53,24 +53,54 @@ pub fn amend_updated() -> GitResult {
 }
 
 /// Changes the author on the last n commits to the current git user.
-pub fn change_author(num: Option<u16>) -> GitResult {
+///
+/// Fails if the commits have been pushed or include a merge, unless `force` is set; with `force`, the root commit can be
+/// included as well.
+pub fn change_author(num: Option<u16>, force: bool) -> GitResult {
     trace!("author() called with: {:#?}", num);
 
+    let num = num.unwrap_or(1);
+    let rewrite = Rewrite::check("reset the author of", num)?;
+    rewrite.refuse_unless(force)?;
+
+    let base = format!("HEAD~{num}");
+    let onto = if rewrite.includes_root {
+        "--root"
+    } else {
+        &base
+    };
+
     GitCommand::new("rebase")
-        .with_default_args(&[
-            &format!("HEAD~{}", num.unwrap_or(1)),
-            "-x",
-            "git commit --amend --no-edit --reset-author",
-        ])
+        .with_default_args(&[onto, "-x", "git commit --amend --no-edit --reset-author"])
         .run()
 }
 
 /// `git reset --mixed HEAD~NUM`
-pub fn undo(num: Option<u16>) -> GitResult {
+///
+/// Fails if the commits have been pushed or include a merge, unless `force` is set; with `force`, every commit can be
+/// undone, leaving the branch unborn.
+pub fn undo(num: Option<u16>, force: bool) -> GitResult {
     trace!("undo() called with: {:#?}", num);
 
+    let num = num.unwrap_or(1);
+    let rewrite = Rewrite::check("undo", num)?;
+    rewrite.refuse_unless(force)?;
+
+    if rewrite.includes_root {
+        // there is no commit to reset to, so delete the branch's ref and unstage everything, as `--mixed` would
+        return match GitCommand::new("update-ref")
+            .with_default_args(&["-d", "HEAD"])
+            .run()?
+        {
+            GitCommandResult::Success => GitCommand::new("read-tree")
+                .with_default_args(&["--empty"])
+                .run(),
+            GitCommandResult::Error => Ok(GitCommandResult::Error),
+        };
+    }
+
     GitCommand::new("reset")
-        .with_default_args(&["--mixed", &format!("HEAD~{}", num.unwrap_or(1))])
+        .with_default_args(&["--mixed", &format!("HEAD~{num}")])
         .run()
 }
 
@@ -110,3 +140,157 @@ pub fn interactive_rebase(num: Option<u16>) -> GitResult {
         .with_default_args(&["--interactive", &format!("HEAD~{}", num.unwrap_or(10))])
         .run()
 }
+
+/// What rewriting the last n commits (`HEAD~N..HEAD`) would affect.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Rewrite {
+    /// e.g. `undo`, for the error messages.
+    action: &'static str,
+    num: u16,
+    /// The affected commits that are reachable from the upstream or a remote-tracking branch, as `short-hash subject`.
+    published: Vec<String>,
+    /// The branches they are on, e.g. `origin/main`.
+    published_on: Vec<String>,
+    /// The affected merge commits, as `short-hash subject`.
+    merges: Vec<String>,
+    /// The first-parent history has exactly `num` commits, so the root commit is affected.
+    includes_root: bool,
+}
+
+impl Rewrite {
+    /// Check the last `num` commits; fails if there aren't that many.
+    fn check(action: &'static str, num: u16) -> Result<Rewrite> {
+        let history = GitCommand::new("rev-list")
+            .with_default_args(&[
+                "--first-parent",
+                &format!("--max-count={}", u32::from(num) + 1),
+                "HEAD",
+            ])
+            .read_stdout()?;
+        let length = history.lines().count();
+
+        if length < usize::from(num) {
+            return Err(anyhow!(
+                "Can't {action} {num} commits; the branch only has {length}"
+            ));
+        }
+        let includes_root = length == usize::from(num);
+
+        // every commit the rewrite would drop or replace, including those brought in by merges
+        let range = if includes_root {
+            "HEAD".to_string()
+        } else {
+            format!("HEAD~{num}..HEAD")
+        };
+
+        let affected = Self::commits(&[&range])?;
+        let merges = Self::commits(&["--min-parents=2", &range])?;
+
+        let upstream = Git::upstream()?;
+
+        let mut not_published_args = vec![range.as_str(), "--not", "--remotes"];
+        not_published_args.extend(upstream.as_deref());
+        let unpublished = Self::commits(&not_published_args)?;
+
+        let published: Vec<String> = affected
+            .into_iter()
+            .filter(|commit| !unpublished.contains(commit))
+            .collect();
+
+        let mut published_on: Vec<String> = Vec::new();
+        for commit in &published {
+            let hash = commit.split_whitespace().next().unwrap_or_default();
+            let branches = GitCommand::new("branch")
+                .with_default_args(&["--all", "--format=%(refname)", "--contains", hash])
+                .read_stdout()?;
+
+            for branch in branches.lines() {
+                let is_remote = branch.starts_with("refs/remotes/") && !branch.ends_with("/HEAD");
+                let name = branch
+                    .trim_start_matches("refs/remotes/")
+                    .trim_start_matches("refs/heads/");
+
+                if (is_remote || Some(branch) == upstream.as_deref())
+                    && !published_on.iter().any(|b| b == name)
+                {
+                    published_on.push(name.to_string());
+                }
+            }
+        }
+
+        Ok(Rewrite {
+            action,
+            num,
+            published,
+            published_on,
+            merges,
+            includes_root,
+        })
+    }
+
+    /// Fail with an explanation of everything that makes the rewrite unsafe, unless `force` is set.
+    fn refuse_unless(&self, force: bool) -> Result<()> {
+        let mut problems: Vec<String> = Vec::new();
+
+        if !self.published.is_empty() {
+            problems.push(format!(
+                "{} of them {} already on {}, so rewriting {} would diverge from what others have:\n{}",
+                self.published.len(),
+                if self.published.len() == 1 { "is" } else { "are" },
+                self.published_on.join(", "),
+                if self.published.len() == 1 { "it" } else { "them" },
+                Self::list(&self.published)
+            ));
+        }
+
+        if !self.merges.is_empty() {
+            problems.push(format!(
+                "the range includes {}, which would be flattened or dropped:\n{}",
+                if self.merges.len() == 1 {
+                    "a merge"
+                } else {
+                    "merges"
+                },
+                Self::list(&self.merges)
+            ));
+        }
+
+        if self.includes_root {
+            problems.push("the range includes the root commit".to_string());
+        }
+
+        if problems.is_empty() || force {
+            return Ok(());
+        }
+
+        Err(anyhow!(
+            "Refusing to {} the last {} commit{}:\n- {}\nPass --force to do it anyway (`git-util oops` can restore the branch afterwards)",
+            self.action,
+            self.num,
+            if self.num == 1 { "" } else { "s" },
+            problems.join("\n- ")
+        ))
+    }
+
+    /// `git log --format=%h %s ARGS`, as `short-hash subject` lines.
+    fn commits(args: &[&str]) -> Result<Vec<String>> {
+        let mut default_args = vec!["--format=%h %s"];
+        default_args.extend_from_slice(args);
+
+        Ok(GitCommand::new("log")
+            .with_default_args(&default_args)
+            .read_stdout()?
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Indent `commits` under the problem they belong to.
+    fn list(commits: &[String]) -> String {
+        commits
+            .iter()
+            .map(|commit| format!("    {commit}"))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,100 @@
+use crate::git::{
+    history::{paint, relative_date},
+    journal::{self, Snapshot, Worktree},
+    pager, GitCommandResult, GitResult,
+};
+use anyhow::anyhow;
+use log::trace;
+use nu_ansi_term::Color;
+use std::{
+    io::{self, IsTerminal},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// List the journal, newest first, numbered the way `restore` takes them.
+pub fn list() -> GitResult {
+    trace!("oops list() called");
+
+    let entries = journal::read()?;
+
+    if entries.is_empty() {
+        println!("The git-util journal is empty");
+        return Ok(GitCommandResult::Success);
+    }
+
+    let colored = io::stdout().is_terminal();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+
+    let dates: Vec<String> = entries
+        .iter()
+        .map(|e| relative_date(now - e.time))
+        .collect();
+    let number_width = entries.len().to_string().len();
+    let date_width = dates.iter().map(|d| d.chars().count()).max().unwrap_or(0);
+
+    let mut output = String::new();
+
+    for (i, (entry, date)) in entries.iter().zip(&dates).enumerate() {
+        let head = match &entry.head {
+            Some(head) => head.chars().take(7).collect(),
+            None => "unborn".to_string(),
+        };
+        let state = match &entry.worktree {
+            Worktree::Stash(_) => " with uncommitted changes",
+            _ => "",
+        };
+        output.push_str(&format!(
+            "{:>number_width$}  {}  {}  {}\n",
+            i + 1,
+            paint(&format!("{date:<date_width$}"), Color::Purple, colored),
+            entry.command,
+            paint(
+                &format!(
+                    "({} at {head}{state})",
+                    entry.branch.as_deref().unwrap_or("detached HEAD")
+                ),
+                Color::DarkGray,
+                colored,
+            ),
+        ));
+    }
+
+    pager::page("oops", output.as_bytes())?;
+
+    Ok(GitCommandResult::Success)
+}
+
+/// Restore journal entry `num` (1 is the most recent), after recording the current state so that it can be restored in
+/// turn.
+pub fn restore(num: usize, command: &str) -> GitResult {
+    trace!("oops restore() called with: {:#?}", num);
+
+    let entries = journal::read()?;
+    let entry = num
+        .checked_sub(1)
+        .and_then(|i| entries.get(i))
+        .ok_or_else(|| {
+            anyhow!(
+                "There is no journal entry {num}; there are {} (see `git-util oops --list`)",
+                entries.len()
+            )
+        })?;
+
+    Snapshot::of_head(command)?.record()?;
+
+    let result = entry.restore()?;
+
+    if result == GitCommandResult::Success {
+        println!(
+            "Restored the state from before `{}` ({} at {})",
+            entry.command,
+            entry.branch.as_deref().unwrap_or("detached HEAD"),
+            entry.head.as_deref().unwrap_or_default()
+        );
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,350 @@
+use anyhow::{anyhow, Result};
+use core::fmt;
+use log::debug;
+use std::{env, path::PathBuf};
+
+use crate::git::{GitCommand, GitResult};
+
+/// Settings used by the **git-util** application, read from the `git-util` section of the Git config.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum GitUtilConfig {
+    /// The format of each changelog entry; defaults to `- {scope}{description} ({short_hash}){refs}`.
+    ChangelogEntryFormat,
+    /// The changelog sections as `type=Title`, in order; defaults to `changelog::DEFAULT_SECTIONS`.
+    ChangelogSections,
+    /// The path of the changelog template file.
+    ChangelogTemplate,
+    /// Require commit message subjects to follow the Conventional Commits grammar.
+    CommitMsgConventional,
+    /// Words (case-insensitive) that may not appear in a commit message on a protected branch.
+    CommitMsgForbiddenWords,
+    /// Maximum length of a line in the commit message body.
+    CommitMsgMaxBodyLineLength,
+    /// Maximum length of the commit message subject line.
+    CommitMsgMaxSubjectLength,
+    /// Require the second line of the commit message to be blank; defaults to `true`.
+    CommitMsgRequireBlankLine,
+    /// Trailer tokens (e.g. `Refs`) that must be present in the commit message.
+    CommitMsgRequiredTrailers,
+    /// If set, Conventional Commits scopes must be one of these values.
+    CommitMsgScopes,
+    /// Allowed Conventional Commits types; defaults to `DEFAULT_CONVENTIONAL_TYPES`.
+    CommitMsgTypes,
+    /// Path globs of files that are exempt from `PreCommitBlockBinary`.
+    PreCommitBinaryAllowedPaths,
+    /// Fail the pre-commit hook if a binary file is staged.
+    PreCommitBlockBinary,
+    /// Fail the pre-commit hook if an archive or executable file is newly added.
+    PreCommitBlockNewArtifacts,
+    /// Check that newly added paths don't differ only in case from tracked paths; defaults to `false`.
+    PreCommitCheckCaseCollisions,
+    /// Check added lines for leftover merge conflict markers; defaults to `false`.
+    PreCommitCheckConflictMarkers,
+    /// Check that changed text files end with a newline; defaults to `false`.
+    PreCommitCheckFinalNewline,
+    /// Check for CRLF line endings added to files that use LF; defaults to `false`.
+    PreCommitCheckLineEndings,
+    /// Check added lines for a space before a tab in the indentation; defaults to `false`.
+    PreCommitCheckMixedIndentation,
+    /// Check that newly added paths can be checked out on Windows and macOS; defaults to `false`.
+    PreCommitCheckPortablePaths,
+    /// Check added lines for trailing whitespace; defaults to `false`.
+    PreCommitCheckTrailingWhitespace,
+    /// Path globs that may not be added, e.g. `.env` or `*.pem`.
+    PreCommitForbiddenPaths,
+    /// The maximum size of a staged file; Git's `k`, `m` and `g` suffixes are supported.
+    PreCommitMaxFileSize,
+    /// The maximum length (in characters) of a newly added path.
+    PreCommitMaxPathLength,
+    /// `Co-authored-by` trailer values to add to new commit messages.
+    PrepareCommitMsgCoAuthors,
+    /// Regexes used to extract a ticket ID from the branch name; defaults to `DEFAULT_TICKET_PATTERN`. The changelog also
+    /// uses them to find references, but only if they are set.
+    PrepareCommitMsgTicketPatterns,
+    /// Where to put the ticket ID: `trailer` (the default), `prefix` or `none`.
+    PrepareCommitMsgTicketPlacement,
+    /// The format of the subject prefix when the placement is `prefix`; `{ticket}` is replaced with the ticket ID.
+    PrepareCommitMsgTicketPrefix,
+    /// The trailer token used when the placement is `trailer`; defaults to `Refs`.
+    PrepareCommitMsgTicketTrailer,
+    /// The prefix of the `git-util.email-rule.<name>.<setting>` settings that make up the author/committer email policy.
+    EmailRules,
+    /// Record the working tree (with `git stash create`) in the undo journal; defaults to `true`.
+    JournalSnapshotWorktree,
+    /// The global `core.hooksPath` that was set before `git-util hook install --global` replaced it.
+    HooksPreviousGlobalPath,
+    /// The prefix of the `git-util.linter.<name>.<setting>` settings for commands run on staged files by the pre-commit hook.
+    Linters,
+    /// Branch name globs that stricter rules are applied to.
+    ProtectedBranches,
+    /// If set, the `gpg.format` that commits must be signed with, e.g. `ssh`.
+    SigningFormat,
+    /// Require commits to be signed: checked in the Git config by the pre-commit hook, and with `git verify-commit` for
+    /// outgoing commits.
+    SigningRequired,
+}
+
+/// The Conventional Commits types that are allowed if `git-util.commit-msg.type` is not set.
+pub const DEFAULT_CONVENTIONAL_TYPES: [&str; 11] = [
+    "build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test",
+];
+
+/// Matches ticket IDs like `ABC-123` if `git-util.prepare-commit-msg.ticket-pattern` is not set.
+pub const DEFAULT_TICKET_PATTERN: &str = r"\b[A-Z][A-Z0-9]+-[0-9]+\b";
+
+impl fmt::Display for GitUtilConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitUtilConfig::ChangelogEntryFormat => write!(f, "git-util.changelog.entry-format"),
+            GitUtilConfig::ChangelogSections => write!(f, "git-util.changelog.section"),
+            GitUtilConfig::ChangelogTemplate => write!(f, "git-util.changelog.template"),
+            GitUtilConfig::CommitMsgConventional => write!(f, "git-util.commit-msg.conventional"),
+            GitUtilConfig::CommitMsgForbiddenWords => {
+                write!(f, "git-util.commit-msg.forbidden-word")
+            }
+            GitUtilConfig::CommitMsgMaxBodyLineLength => {
+                write!(f, "git-util.commit-msg.max-body-line-length")
+            }
+            GitUtilConfig::CommitMsgMaxSubjectLength => {
+                write!(f, "git-util.commit-msg.max-subject-length")
+            }
+            GitUtilConfig::CommitMsgRequireBlankLine => {
+                write!(f, "git-util.commit-msg.require-blank-line")
+            }
+            GitUtilConfig::CommitMsgRequiredTrailers => {
+                write!(f, "git-util.commit-msg.required-trailer")
+            }
+            GitUtilConfig::CommitMsgScopes => write!(f, "git-util.commit-msg.scope"),
+            GitUtilConfig::CommitMsgTypes => write!(f, "git-util.commit-msg.type"),
+            GitUtilConfig::EmailRules => write!(f, "git-util.email-rule"),
+            GitUtilConfig::HooksPreviousGlobalPath => {
+                write!(f, "git-util.hooks.previous-global-path")
+            }
+            GitUtilConfig::JournalSnapshotWorktree => {
+                write!(f, "git-util.journal.snapshot-worktree")
+            }
+            GitUtilConfig::Linters => write!(f, "git-util.linter"),
+            GitUtilConfig::PreCommitBinaryAllowedPaths => {
+                write!(f, "git-util.pre-commit.binary-allowed-path")
+            }
+            GitUtilConfig::PreCommitBlockBinary => write!(f, "git-util.pre-commit.block-binary"),
+            GitUtilConfig::PreCommitBlockNewArtifacts => {
+                write!(f, "git-util.pre-commit.block-new-artifacts")
+            }
+            GitUtilConfig::PreCommitCheckCaseCollisions => {
+                write!(f, "git-util.pre-commit.check-case-collisions")
+            }
+            GitUtilConfig::PreCommitCheckConflictMarkers => {
+                write!(f, "git-util.pre-commit.check-conflict-markers")
+            }
+            GitUtilConfig::PreCommitCheckFinalNewline => {
+                write!(f, "git-util.pre-commit.check-final-newline")
+            }
+            GitUtilConfig::PreCommitCheckLineEndings => {
+                write!(f, "git-util.pre-commit.check-line-endings")
+            }
+            GitUtilConfig::PreCommitCheckMixedIndentation => {
+                write!(f, "git-util.pre-commit.check-mixed-indentation")
+            }
+            GitUtilConfig::PreCommitCheckPortablePaths => {
+                write!(f, "git-util.pre-commit.check-portable-paths")
+            }
+            GitUtilConfig::PreCommitCheckTrailingWhitespace => {
+                write!(f, "git-util.pre-commit.check-trailing-whitespace")
+            }
+            GitUtilConfig::PreCommitForbiddenPaths => {
+                write!(f, "git-util.pre-commit.forbidden-path")
+            }
+            GitUtilConfig::PreCommitMaxFileSize => write!(f, "git-util.pre-commit.max-file-size"),
+            GitUtilConfig::PreCommitMaxPathLength => {
+                write!(f, "git-util.pre-commit.max-path-length")
+            }
+            GitUtilConfig::PrepareCommitMsgCoAuthors => {
+                write!(f, "git-util.prepare-commit-msg.co-author")
+            }
+            GitUtilConfig::PrepareCommitMsgTicketPatterns => {
+                write!(f, "git-util.prepare-commit-msg.ticket-pattern")
+            }
+            GitUtilConfig::PrepareCommitMsgTicketPlacement => {
+                write!(f, "git-util.prepare-commit-msg.ticket-placement")
+            }
+            GitUtilConfig::PrepareCommitMsgTicketPrefix => {
+                write!(f, "git-util.prepare-commit-msg.ticket-prefix")
+            }
+            GitUtilConfig::PrepareCommitMsgTicketTrailer => {
+                write!(f, "git-util.prepare-commit-msg.ticket-trailer")
+            }
+            GitUtilConfig::ProtectedBranches => write!(f, "git-util.protected-branch"),
+            GitUtilConfig::SigningFormat => write!(f, "git-util.signing.format"),
+            GitUtilConfig::SigningRequired => write!(f, "git-util.signing.required"),
+        }
+    }
+}
+
+impl From<GitUtilConfig> for String {
+    fn from(value: GitUtilConfig) -> Self {
+        value.to_string()
+    }
+}
+
+impl GitUtilConfig {
+    /// The last value set for the setting, or `None` if it is not set.
+    pub fn get(self) -> Result<Option<String>> {
+        Ok(self.get_all()?.pop())
+    }
+
+    /// All of the values set for a multi-valued setting.
+    pub fn get_all(self) -> Result<Vec<String>> {
+        get_all(&self.to_string(), None)
+    }
+
+    /// The setting interpreted as a boolean (`true/false`, `yes/no`, `on/off`, `1/0`).
+    pub fn get_bool(self) -> Result<Option<bool>> {
+        match get_all(&self.to_string(), Some("bool"))?.pop() {
+            Some(value) => Ok(Some(value == "true")),
+            None => Ok(None),
+        }
+    }
+
+    /// The setting interpreted as an integer; Git's `k`, `m` and `g` suffixes are supported.
+    pub fn get_usize(self) -> Result<Option<usize>> {
+        match get_all(&self.to_string(), Some("int"))?.pop() {
+            Some(value) => Ok(Some(value.parse().map_err(|_| {
+                anyhow!(
+                    "{} must be a non-negative integer; found \"{}\"",
+                    self,
+                    value
+                )
+            })?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Read a (possibly multi-valued) setting from the Git config, optionally normalizing it to `kind`.
+///
+/// `git config [--type=KIND] --get-all KEY`
+pub fn get_all(key: &str, kind: Option<&str>) -> Result<Vec<String>> {
+    let type_arg = format!("--type={}", kind.unwrap_or_default());
+    let args: Vec<&str> = match kind {
+        Some(_) => vec![&type_arg, "--get-all", key],
+        None => vec!["--get-all", key],
+    };
+
+    read(key, &args)
+}
+
+/// Read a setting from a single config file, e.g. `--global` or `--local`.
+///
+/// `git config SCOPE --get KEY`
+pub fn get_scoped(key: &str, scope: &str) -> Result<Option<String>> {
+    Ok(read(key, &[scope, "--get", key])?.pop())
+}
+
+/// Set a setting in the config file for `scope`, e.g. `--global` or `--local`.
+///
+/// `git config SCOPE KEY VALUE`
+pub fn set_scoped(key: &str, value: &str, scope: &str) -> GitResult {
+    GitCommand::new("config")
+        .with_default_args(&[scope, key, value])
+        .run()
+}
+
+/// Remove a setting from the config file for `scope`, e.g. `--global` or `--local`.
+///
+/// `git config SCOPE --unset KEY`
+pub fn unset_scoped(key: &str, scope: &str) -> GitResult {
+    GitCommand::new("config")
+        .with_default_args(&[scope, "--unset", key])
+        .run()
+}
+
+/// Read every setting whose key matches the Regex `pattern`, as `(key, value)` pairs in config order.
+///
+/// `git config --get-regexp PATTERN`
+pub fn get_regexp(pattern: &str) -> Result<Vec<(String, String)>> {
+    Ok(read(pattern, &["--get-regexp", pattern])?
+        .into_iter()
+        .map(|line| match line.split_once(' ') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (line, String::new()),
+        })
+        .collect())
+}
+
+/// The settings of one `<section>.<name>.<setting>` subsection, e.g. `git-util.linter.<name>.command`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Subsection {
+    pub name: String,
+    /// `(key, setting, value)` in config order, where `key` is the full key (e.g. for error messages); Git lowercases
+    /// `setting`.
+    pub settings: Vec<(String, String, String)>,
+}
+
+/// Read every `<section>.<name>.<setting>` setting of `section`, grouped by name, in the order each name first
+/// appears in the config.
+pub fn get_subsections(section: GitUtilConfig) -> Result<Vec<Subsection>> {
+    let prefix = format!("{section}.");
+    let mut subsections: Vec<Subsection> = Vec::new();
+
+    for (key, value) in get_regexp(&format!("^{}\\.", regex::escape(&section.to_string())))? {
+        let Some((name, setting)) = key
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.rsplit_once('.'))
+        else {
+            continue;
+        };
+        let setting = (key.clone(), setting.to_string(), value);
+
+        match subsections.iter_mut().find(|s| s.name == name) {
+            Some(subsection) => subsection.settings.push(setting),
+            None => subsections.push(Subsection {
+                name: name.to_string(),
+                settings: vec![setting],
+            }),
+        }
+    }
+
+    Ok(subsections)
+}
+
+/// The user's home directory (`$HOME`, or `%USERPROFILE%` on Windows).
+pub fn home_dir() -> Result<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("Failed to determine home directory"))
+}
+
+/// Expand a leading `~/` in a path setting, as Git does for pathname settings.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Ok(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Run `git config ARGS`, where `ARGS` reads `key`, and return the value(s).
+fn read(key: &str, args: &[&str]) -> Result<Vec<String>> {
+    let output = GitCommand::new("config")
+        .with_default_args(args)
+        .capture()?;
+
+    match output.status.code() {
+        Some(0) => {
+            let values: Vec<String> = String::from_utf8(output.stdout)?
+                .lines()
+                .map(str::to_string)
+                .collect();
+            debug!("{key}={:?}", values);
+            Ok(values)
+        }
+        // exit code 1 means the key is not set
+        Some(1) => Ok(Vec::new()),
+        _ => Err(anyhow!(
+            "Failed to read config value {}: {}",
+            key,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
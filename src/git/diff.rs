@@ -0,0 +1,191 @@
+/// The changes to a single file in a unified diff, as produced by `git diff --patch`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FilePatch {
+    /// The path of the file after the change (or before it, if the file was deleted).
+    pub path: String,
+    pub new_file: bool,
+    pub deleted_file: bool,
+    pub binary: bool,
+    /// The lines added by the change, with their line numbers in the new version of the file.
+    pub added_lines: Vec<AddedLine>,
+    /// Whether any of the unchanged or removed lines in the hunks end with LF or CRLF, i.e. the line endings the file
+    /// already used.
+    pub existing_lf: bool,
+    pub existing_crlf: bool,
+}
+
+/// A line added by a change.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AddedLine {
+    /// The 1-based line number in the new version of the file.
+    pub number: usize,
+    /// The text of the line, without the leading `+` or the line ending.
+    pub text: String,
+    /// The line ends with CRLF rather than LF.
+    pub crlf: bool,
+    /// The line is the last line of the file and has no line ending.
+    pub no_newline: bool,
+}
+
+/// Parse the output of a `git diff --patch` style command into one `FilePatch` per changed file.
+pub fn parse_patch(patch: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut in_hunk = false;
+    let mut next_line: usize = 0;
+    let mut previous_added = false;
+
+    // `str::lines` would strip the `\r` of CRLF line endings
+    for line in patch.split('\n') {
+        let (line, crlf) = match line.strip_suffix('\r') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            in_hunk = false;
+            files.push(FilePatch {
+                // overwritten by the `+++` header if the file has text changes
+                path: path_from_diff_header(header),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if in_hunk {
+            let first = line.chars().next();
+
+            match first {
+                Some('+') => {
+                    file.added_lines.push(AddedLine {
+                        number: next_line,
+                        text: line[1..].to_string(),
+                        crlf,
+                        no_newline: false,
+                    });
+                    next_line += 1;
+                }
+                Some(' ') | Some('-') => {
+                    if crlf {
+                        file.existing_crlf = true;
+                    } else {
+                        file.existing_lf = true;
+                    }
+
+                    if first == Some(' ') {
+                        next_line += 1;
+                    }
+                }
+                Some('@') => next_line = hunk_start(line),
+                // `\ No newline at end of file` applies to the line before it
+                Some('\\') if previous_added => {
+                    if let Some(added) = file.added_lines.last_mut() {
+                        added.no_newline = true;
+                    }
+                }
+                _ => (),
+            }
+
+            previous_added = first == Some('+');
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+            next_line = hunk_start(line);
+        } else if line.starts_with("new file mode") {
+            file.new_file = true;
+        } else if line.starts_with("deleted file mode") {
+            file.deleted_file = true;
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            file.binary = true;
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if path != "/dev/null" {
+                // Git ends the name with a tab if it contains a space
+                let path = unquote(path.strip_suffix('\t').unwrap_or(path));
+                file.path = path.strip_prefix("b/").unwrap_or(&path).to_string();
+            }
+        }
+    }
+
+    files
+}
+
+/// The starting line number of the new file in a hunk header: `@@ -a,b +c,d @@`.
+fn hunk_start(header: &str) -> usize {
+    header
+        .split(' ')
+        .find_map(|part| part.strip_prefix('+'))
+        .and_then(|range| range.split(',').next())
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Get the new path from `a/OLD b/NEW`, where either path may be quoted.
+fn path_from_diff_header(header: &str) -> String {
+    // a `"` inside a quoted path is escaped, so ` "b/` can only start the quoted new path
+    let new = match header.rfind(" \"b/") {
+        Some(i) if header.ends_with('"') => &header[i + 1..],
+        _ => match header.rfind(" b/") {
+            Some(i) => &header[i + 1..],
+            None => header,
+        },
+    };
+
+    let new = unquote(new);
+    new.strip_prefix("b/").unwrap_or(&new).to_string()
+}
+
+/// Git wraps paths containing unusual characters in double quotes and escapes them as in C, e.g. `"\303\244.txt"` for
+/// `ä.txt` (with the default `core.quotePath`), `"a\tb"` or `"a\\b"`; other paths are returned as they are.
+fn unquote(path: &str) -> String {
+    let Some(quoted) = path
+        .strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+    else {
+        return path.to_string();
+    };
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(quoted.len());
+    let mut chars = quoted.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+
+        match chars.next() {
+            Some('a') => bytes.push(0x07),
+            Some('b') => bytes.push(0x08),
+            Some('t') => bytes.push(b'\t'),
+            Some('n') => bytes.push(b'\n'),
+            Some('v') => bytes.push(0x0b),
+            Some('f') => bytes.push(0x0c),
+            Some('r') => bytes.push(b'\r'),
+            // up to three octal digits, each escaping one byte of a UTF-8 sequence
+            Some(digit @ '0'..='7') => {
+                let mut value = digit.to_digit(8).unwrap_or_default();
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(next) => {
+                            value = value * 8 + next;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            // `\"` and `\\`
+            Some(other) => {
+                let mut buffer = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buffer).as_bytes());
+            }
+            None => bytes.push(b'\\'),
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,588 @@
+use anyhow::{anyhow, Result};
+use nu_ansi_term::{Color, Style};
+use std::{
+    sync::atomic::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use self::tracking::Tracking;
+use crate::{
+    git::{GitCommand, DRY_RUN},
+    json::Json,
+    print::Print,
+};
+
+pub mod changelog;
+pub mod files;
+pub mod filter;
+pub mod graph;
+pub mod stats;
+pub mod tracking;
+
+/// Separates the commits in the `git log` output.
+const RECORD_SEPARATOR: char = '\x1e';
+
+/// Separates the fields of each commit in the `git log` output.
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// The `git log --format` that `Commit::parse` reads: `%x1e` followed by the fields separated by `%x1f`.
+const LOG_FORMAT: &str = "--format=%x1e%H%x1f%h%x1f%P%x1f%at%x1f%aL%x1f%D%x1f%s";
+
+/// The `git log --format` that `CommitRecord::parse` reads; the trailing `%x1f` separates the fields from the `--numstat`
+/// output that follows them.
+const RECORD_LOG_FORMAT: &str =
+    "--format=%x1e%H%x1f%P%x1f%an%x1f%ae%x1f%aI%x1f%cn%x1f%ce%x1f%cI%x1f%s%x1f%b%x1f%(trailers:only,unfold)%x1f";
+
+/// The columns of the `csv` format, in order.
+const CSV_COLUMNS: [&str; 14] = [
+    "hash",
+    "parents",
+    "author_name",
+    "author_email",
+    "author_date",
+    "committer_name",
+    "committer_email",
+    "committer_date",
+    "subject",
+    "body",
+    "trailers",
+    "files_changed",
+    "insertions",
+    "deletions",
+];
+
+/// Subjects aren't truncated below this many characters, even if that means the line wraps.
+const MIN_SUBJECT_WIDTH: usize = 20;
+
+/// A commit read from `git log`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Commit {
+    pub hash: String,
+    pub short_hash: String,
+    /// The parents' hashes; rewritten to the nearest shown ancestors if `--parents` is given with a path filter.
+    pub parents: Vec<String>,
+    /// The author date as a Unix timestamp.
+    pub author_date: i64,
+    /// The local part of the author's email, e.g. `jane` for `jane@example.com`.
+    pub author: String,
+    /// The ref names pointing at the commit, e.g. `HEAD -> main`, `origin/main` and `tag: v1.0`.
+    pub refs: Vec<String>,
+    pub subject: String,
+}
+
+impl Commit {
+    /// Read the commits selected by `git log OPTIONS ARGS`; both can include anything `git log` accepts except a format.
+    pub fn read(options: &[&str], args: &[String]) -> Result<Vec<Commit>> {
+        let default_args: Vec<&str> = [LOG_FORMAT].iter().chain(options).copied().collect();
+
+        read_log(&default_args, args)?
+            .split(RECORD_SEPARATOR)
+            .filter(|record| !record.trim().is_empty())
+            .map(Commit::parse)
+            .collect()
+    }
+
+    fn parse(record: &str) -> Result<Commit> {
+        let fields: Vec<&str> = record
+            .trim_end_matches('\n')
+            .split(FIELD_SEPARATOR)
+            .collect();
+
+        let [hash, short_hash, parents, author_date, author, refs, subject] = fields[..] else {
+            return Err(anyhow!("Failed to parse 'git log' record \"{record}\""));
+        };
+
+        Ok(Commit {
+            hash: hash.to_string(),
+            short_hash: short_hash.to_string(),
+            parents: parents.split_whitespace().map(str::to_string).collect(),
+            author_date: author_date.parse().unwrap_or_default(),
+            author: author.to_string(),
+            refs: refs
+                .split(", ")
+                .filter(|r| !r.is_empty())
+                .map(str::to_string)
+                .collect(),
+            subject: subject.to_string(),
+        })
+    }
+}
+
+/// `git log DEFAULT_ARGS ARGS`; with `DRY_RUN`, the command is printed instead and nothing is read.
+fn read_log(default_args: &[&str], args: &[String]) -> Result<String> {
+    let log = GitCommand::new("log");
+    let with_defaults = log.with_default_args(default_args);
+    let command = with_defaults.with_user_args(args);
+
+    if DRY_RUN.load(Ordering::SeqCst) {
+        Print::stderr_purple(&format!(
+            "command that would be run: `{}`",
+            command.construct_git_command_string()
+        ));
+        return Ok(String::new());
+    }
+
+    command.read_stdout()
+}
+
+/// The machine-readable formats commit records can be written in.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum RecordFormat {
+    /// A single JSON array of commit objects.
+    Json,
+    /// One row per commit, with the file stats summed; see `CSV_COLUMNS`.
+    Csv,
+    /// One JSON commit object per line.
+    Ndjson,
+}
+
+/// The name and email of a commit's author or committer.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+}
+
+/// The lines changed in one file by a commit, from `git log --numstat`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FileStat {
+    pub path: String,
+    /// The path before the commit, if the file was renamed or copied.
+    pub old_path: Option<String>,
+    /// `None` for binary files.
+    pub insertions: Option<usize>,
+    /// `None` for binary files.
+    pub deletions: Option<usize>,
+}
+
+/// Everything about a commit that the `--format` output includes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CommitRecord {
+    pub hash: String,
+    pub parents: Vec<String>,
+    pub author: Identity,
+    /// Strict ISO 8601, e.g. `2024-05-01T12:34:56+02:00`.
+    pub author_date: String,
+    pub committer: Identity,
+    /// Strict ISO 8601, e.g. `2024-05-01T12:34:56+02:00`.
+    pub committer_date: String,
+    pub subject: String,
+    /// The message after the subject, including any trailers.
+    pub body: String,
+    /// `(key, value)` pairs, in message order, with folded values unfolded.
+    pub trailers: Vec<(String, String)>,
+    pub files: Vec<FileStat>,
+}
+
+impl CommitRecord {
+    /// Read the commits selected by `git log --numstat OPTIONS ARGS`.
+    pub fn read(options: &[&str], args: &[String]) -> Result<Vec<CommitRecord>> {
+        let default_args: Vec<&str> = [RECORD_LOG_FORMAT, "--numstat"]
+            .iter()
+            .chain(options)
+            .copied()
+            .collect();
+
+        read_log(&default_args, args)?
+            .split(RECORD_SEPARATOR)
+            .filter(|record| !record.trim().is_empty())
+            .map(CommitRecord::parse)
+            .collect()
+    }
+
+    fn parse(record: &str) -> Result<CommitRecord> {
+        let fields: Vec<&str> = record.split(FIELD_SEPARATOR).collect();
+
+        let [hash, parents, author_name, author_email, author_date, committer_name, committer_email, committer_date, subject, body, trailers, numstat] =
+            fields[..]
+        else {
+            return Err(anyhow!("Failed to parse 'git log' record \"{record}\""));
+        };
+
+        Ok(CommitRecord {
+            hash: hash.to_string(),
+            parents: parents.split_whitespace().map(str::to_string).collect(),
+            author: Identity {
+                name: author_name.to_string(),
+                email: author_email.to_string(),
+            },
+            author_date: author_date.to_string(),
+            committer: Identity {
+                name: committer_name.to_string(),
+                email: committer_email.to_string(),
+            },
+            committer_date: committer_date.to_string(),
+            subject: subject.to_string(),
+            body: body.trim_end().to_string(),
+            trailers: trailers
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect(),
+            files: numstat.lines().filter_map(FileStat::parse).collect(),
+        })
+    }
+
+    fn to_json(&self) -> Json {
+        let identity = |identity: &Identity| {
+            Json::object([
+                ("name", identity.name.as_str().into()),
+                ("email", identity.email.as_str().into()),
+            ])
+        };
+
+        Json::object([
+            ("hash", self.hash.as_str().into()),
+            ("parents", self.parents.clone().into()),
+            ("author", identity(&self.author)),
+            ("author_date", self.author_date.as_str().into()),
+            ("committer", identity(&self.committer)),
+            ("committer_date", self.committer_date.as_str().into()),
+            ("subject", self.subject.as_str().into()),
+            ("body", self.body.as_str().into()),
+            (
+                "trailers",
+                Json::Array(
+                    self.trailers
+                        .iter()
+                        .map(|(key, value)| {
+                            Json::object([
+                                ("key", key.as_str().into()),
+                                ("value", value.as_str().into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "files",
+                Json::Array(
+                    self.files
+                        .iter()
+                        .map(|file| {
+                            Json::object([
+                                ("path", file.path.as_str().into()),
+                                ("old_path", file.old_path.clone().into()),
+                                ("insertions", file.insertions.into()),
+                                ("deletions", file.deletions.into()),
+                                ("binary", file.insertions.is_none().into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
+    fn to_csv_row(&self) -> String {
+        let sum = |count: fn(&FileStat) -> Option<usize>| {
+            self.files
+                .iter()
+                .filter_map(count)
+                .sum::<usize>()
+                .to_string()
+        };
+
+        let trailers: Vec<String> = self
+            .trailers
+            .iter()
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect();
+
+        let cells = [
+            self.hash.clone(),
+            self.parents.join(" "),
+            self.author.name.clone(),
+            self.author.email.clone(),
+            self.author_date.clone(),
+            self.committer.name.clone(),
+            self.committer.email.clone(),
+            self.committer_date.clone(),
+            self.subject.clone(),
+            self.body.clone(),
+            trailers.join("\n"),
+            self.files.len().to_string(),
+            sum(|f| f.insertions),
+            sum(|f| f.deletions),
+        ];
+
+        cells
+            .iter()
+            .map(|cell| csv_cell(cell))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+impl FileStat {
+    /// Parse a `--numstat` line: `INSERTIONS<TAB>DELETIONS<TAB>PATH`, where the counts are `-` for binary files and a
+    /// rename is written as `old => new` or `dir/{old => new}/file`.
+    fn parse(line: &str) -> Option<FileStat> {
+        let mut parts = line.splitn(3, '\t');
+        let insertions = parts.next()?;
+        let deletions = parts.next()?;
+        let path = parts.next()?;
+
+        let (path, old_path) = match path.split_once(" => ") {
+            None => (path.to_string(), None),
+            Some((before, after)) => match (before.rsplit_once('{'), after.split_once('}')) {
+                // either side of the braces can be empty, e.g. `{ => src}/main.rs`
+                (Some((prefix, old)), Some((new, suffix))) => (
+                    join_rename_path(prefix, new, suffix),
+                    Some(join_rename_path(prefix, old, suffix)),
+                ),
+                _ => (after.to_string(), Some(before.to_string())),
+            },
+        };
+
+        Some(FileStat {
+            path,
+            old_path,
+            insertions: insertions.parse().ok(),
+            deletions: deletions.parse().ok(),
+        })
+    }
+}
+
+fn join_rename_path(prefix: &str, middle: &str, suffix: &str) -> String {
+    format!("{prefix}{middle}{suffix}")
+        .replace("//", "/")
+        .trim_start_matches('/')
+        .to_string()
+}
+
+/// Render `records` in `format`; the output ends with a newline.
+pub fn render_records(records: &[CommitRecord], format: RecordFormat) -> String {
+    match format {
+        RecordFormat::Json => format!(
+            "{}\n",
+            Json::Array(records.iter().map(CommitRecord::to_json).collect())
+        ),
+        RecordFormat::Ndjson => records
+            .iter()
+            .map(|record| format!("{}\n", record.to_json()))
+            .collect(),
+        RecordFormat::Csv => {
+            let mut output = format!("{}\n", CSV_COLUMNS.join(","));
+            for record in records {
+                output.push_str(&record.to_csv_row());
+                output.push('\n');
+            }
+            output
+        }
+    }
+}
+
+/// Render `commits` one per line, with aligned columns: short hash, relative author date, author, refs and subject.
+///
+/// If `width` is set, each line is truncated to fit by shortening the subject. Colors are only used if `colored`.
+pub fn render_one_line(
+    commits: &[Commit],
+    tracking: Option<&Tracking>,
+    colored: bool,
+    width: Option<usize>,
+) -> String {
+    let columns = OneLineColumns::new(commits, colored).mark_tracking(tracking);
+
+    (0..commits.len())
+        .map(|index| format!("{}\n", columns.line(index, width)))
+        .collect()
+}
+
+/// Lays out commits one per line, with the hash, date and author columns padded to the widest value.
+struct OneLineColumns<'a> {
+    commits: &'a [Commit],
+    dates: Vec<String>,
+    hash_width: usize,
+    date_width: usize,
+    author_width: usize,
+    colored: bool,
+    /// A ref to emphasize in the decorations, e.g. `origin/main`.
+    emphasized_ref: Option<&'a str>,
+    /// If set, each line starts with a mark for commits that haven't been pushed or pulled.
+    tracking: Option<&'a Tracking>,
+}
+
+impl<'a> OneLineColumns<'a> {
+    fn new(commits: &'a [Commit], colored: bool) -> OneLineColumns<'a> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+
+        let dates: Vec<String> = commits
+            .iter()
+            .map(|c| relative_date(now - c.author_date))
+            .collect();
+
+        OneLineColumns {
+            commits,
+            hash_width: column_width(commits.iter().map(|c| c.short_hash.as_str())),
+            date_width: column_width(dates.iter().map(String::as_str)),
+            author_width: column_width(commits.iter().map(|c| c.author.as_str())),
+            dates,
+            colored,
+            emphasized_ref: None,
+            tracking: None,
+        }
+    }
+
+    fn emphasize_ref(mut self, name: Option<&'a str>) -> OneLineColumns<'a> {
+        self.emphasized_ref = name;
+        self
+    }
+
+    fn mark_tracking(mut self, tracking: Option<&'a Tracking>) -> OneLineColumns<'a> {
+        self.tracking = tracking;
+        self
+    }
+
+    /// The line for `commits[index]`, without a newline, truncated to `width` if set.
+    fn line(&self, index: usize, width: Option<usize>) -> String {
+        let commit = &self.commits[index];
+        let mut line = String::new();
+
+        // the decorations are followed by a space, as well as being wrapped in parentheses
+        let refs_width = if commit.refs.is_empty() {
+            0
+        } else {
+            commit.refs.join(", ").chars().count() + 3
+        };
+
+        // hash, date and author are followed by a space each, as is the mark
+        let mark_width = if self.tracking.is_some() { 2 } else { 0 };
+        let prefix_width =
+            mark_width + self.hash_width + self.date_width + self.author_width + 3 + refs_width;
+        let subject = match width {
+            Some(width) => truncate(
+                &commit.subject,
+                width.saturating_sub(prefix_width).max(MIN_SUBJECT_WIDTH),
+            ),
+            None => commit.subject.clone(),
+        };
+
+        if let Some(tracking) = self.tracking {
+            match tracking.mark(&commit.hash) {
+                Some((mark, color)) => {
+                    line.push_str(&paint(&mark.to_string(), color, self.colored))
+                }
+                None => line.push(' '),
+            }
+            line.push(' ');
+        }
+
+        let columns = [
+            (pad(&commit.short_hash, self.hash_width), Color::Yellow),
+            (pad(&self.dates[index], self.date_width), Color::Purple),
+            (pad(&commit.author, self.author_width), Color::Blue),
+        ];
+
+        for (text, color) in columns {
+            line.push_str(&paint(&text, color, self.colored));
+            line.push(' ');
+        }
+
+        if !commit.refs.is_empty() {
+            let decorations: Vec<String> = commit
+                .refs
+                .iter()
+                .map(|r| {
+                    let style = if Some(r.as_str()) == self.emphasized_ref {
+                        ref_color(r).bold()
+                    } else {
+                        ref_color(r).normal()
+                    };
+                    paint(r, style, self.colored)
+                })
+                .collect();
+            line.push_str(&format!("({}) ", decorations.join(", ")));
+        }
+
+        line.push_str(&paint(&subject, Color::Cyan, self.colored));
+        line
+    }
+}
+
+/// Describe an age in seconds like Git's `--date=relative`, e.g. `5 minutes ago` or `2 years ago`.
+pub fn relative_date(seconds: i64) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const WEEK: i64 = 7 * DAY;
+    const MONTH: i64 = 30 * DAY;
+    const YEAR: i64 = 365 * DAY;
+
+    if seconds < 0 {
+        return "in the future".to_string();
+    }
+
+    let (count, unit) = match seconds {
+        s if s < 90 => (s, "second"),
+        s if s < 90 * MINUTE => (s / MINUTE, "minute"),
+        s if s < 36 * HOUR => (s / HOUR, "hour"),
+        s if s < 14 * DAY => (s / DAY, "day"),
+        s if s < 10 * WEEK => (s / WEEK, "week"),
+        s if s < YEAR => (s / MONTH, "month"),
+        s => (s / YEAR, "year"),
+    };
+
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+/// Quote `value` for CSV if it contains a comma, quote or line break, doubling any quotes.
+fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Colors ref decorations the way `git log --decorate` does: `HEAD` cyan, tags yellow, remote-tracking branches red and
+/// local branches green.
+fn ref_color(name: &str) -> Color {
+    if name.starts_with("HEAD") {
+        Color::Cyan
+    } else if name.starts_with("tag: ") {
+        Color::Yellow
+    } else if name.contains('/') {
+        Color::Red
+    } else {
+        Color::Green
+    }
+}
+
+/// The width of the widest of `values`, in characters.
+fn column_width<'a>(values: impl Iterator<Item = &'a str>) -> usize {
+    values.map(|v| v.chars().count()).max().unwrap_or(0)
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!("{text:<width$}")
+}
+
+/// Shorten `text` to at most `width` characters, ending it with `…` if anything was cut.
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+
+    match width {
+        0 => String::new(),
+        _ => text.chars().take(width - 1).chain(['…']).collect(),
+    }
+}
+
+/// `text` in `style`, if `colored`.
+pub fn paint(text: &str, style: impl Into<Style>, colored: bool) -> String {
+    if colored {
+        style.into().paint(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
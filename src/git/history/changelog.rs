@@ -0,0 +1,381 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use super::CommitRecord;
+use crate::git::{config::GitUtilConfig, message::ConventionalSubject, GitCommand};
+
+/// The sections of the changelog if `git-util.changelog.section` is not set, as `type=Title`, in order.
+pub const DEFAULT_SECTIONS: [&str; 12] = [
+    "feat=Features",
+    "fix=Bug Fixes",
+    "perf=Performance Improvements",
+    "revert=Reverts",
+    "refactor=Code Refactoring",
+    "docs=Documentation",
+    "build=Build System",
+    "ci=Continuous Integration",
+    "test=Tests",
+    "style=Styles",
+    "chore=Chores",
+    "other=Other Changes",
+];
+
+/// The format of each entry if `git-util.changelog.entry-format` is not set.
+const DEFAULT_ENTRY_FORMAT: &str = "- {scope}{description} ({short_hash}){refs}";
+
+/// The layout of the changelog if neither `--template` nor `git-util.changelog.template` is set.
+const DEFAULT_TEMPLATE: &str = "## {version} ({date})\n\n{breaking}{sections}";
+
+/// The section key for commits that don't follow Conventional Commits, or whose type has no section of its own.
+const OTHER: &str = "other";
+
+/// Matches issue references like `#123`, in addition to the ticket patterns.
+const ISSUE_PATTERN: &str = r"#[0-9]+";
+
+/// A commit, as it appears in the changelog.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Entry {
+    /// The Conventional Commits type, or `None` if the subject doesn't follow Conventional Commits.
+    pub kind: Option<String>,
+    pub scope: Option<String>,
+    /// The subject's description, or the whole subject if it doesn't follow Conventional Commits.
+    pub description: String,
+    /// The `BREAKING CHANGE` notes; the description if the subject is marked with `!` but there are none.
+    pub breaking: Vec<String>,
+    pub hash: String,
+    pub author: String,
+    /// Issue and ticket references found in the body and trailers, e.g. `#12` or `ABC-123`.
+    pub refs: Vec<String>,
+}
+
+/// The changes between two revisions, grouped into sections when rendered.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Changelog {
+    /// The tag or revision the changelog ends at, or `Unreleased` for `HEAD`.
+    pub version: String,
+    /// The commit date of the newest commit, e.g. `2024-05-01`.
+    pub date: String,
+    /// Newest first.
+    pub entries: Vec<Entry>,
+}
+
+/// How the changelog is rendered, from the `git-util.changelog.*` settings.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChangelogFormat {
+    /// `(type, title)` pairs, in order; types without a section are listed under `other`, if it has one.
+    pub sections: Vec<(String, String)>,
+    pub entry: String,
+    pub template: String,
+}
+
+impl Changelog {
+    /// Read the non-merge commits in `range` (`FROM..TO`, or `FROM` for `FROM..HEAD`); by default, since the latest tag.
+    pub fn read(range: Option<&str>) -> Result<Changelog> {
+        let range = match range {
+            Some(range) if range.contains("..") => range.to_string(),
+            Some(from) => format!("{from}..HEAD"),
+            None => match latest_tag()? {
+                Some(tag) => format!("{tag}..HEAD"),
+                None => "HEAD".to_string(),
+            },
+        };
+
+        let to = match range.rsplit_once("..") {
+            Some((_, to)) => to.trim_start_matches('.'),
+            None => range.as_str(),
+        };
+        let version = match to {
+            "" | "HEAD" => "Unreleased".to_string(),
+            to => to.to_string(),
+        };
+
+        let records = CommitRecord::read(&["--no-merges"], std::slice::from_ref(&range))?;
+        let ref_patterns = ref_patterns()?;
+
+        Ok(Changelog {
+            version,
+            date: records
+                .first()
+                .map(|record| record.committer_date.chars().take(10).collect())
+                .unwrap_or_default(),
+            entries: records
+                .iter()
+                .map(|record| Entry::from_record(record, &ref_patterns))
+                .collect(),
+        })
+    }
+
+    /// Render the changelog with `format`'s template: the breaking changes first, then a section per type, with scoped
+    /// entries grouped by scope after the unscoped ones.
+    pub fn render(&self, format: &ChangelogFormat) -> String {
+        let breaking: Vec<String> = self
+            .entries
+            .iter()
+            .flat_map(|entry| {
+                entry.breaking.iter().map(|note| {
+                    let note = Entry {
+                        description: note.clone(),
+                        ..entry.clone()
+                    };
+                    note.render(&format.entry)
+                })
+            })
+            .collect();
+
+        let mut sections = String::new();
+
+        for (kind, title) in &format.sections {
+            let mut entries: Vec<&Entry> = self
+                .entries
+                .iter()
+                .filter(|entry| format.section_of(entry) == Some(kind))
+                .collect();
+
+            if entries.is_empty() {
+                continue;
+            }
+
+            // stable, so entries with the same scope stay newest first
+            entries.sort_by(|a, b| a.scope.cmp(&b.scope));
+
+            sections.push_str(&section(
+                title,
+                entries.iter().map(|entry| entry.render(&format.entry)),
+            ));
+        }
+
+        let breaking = if breaking.is_empty() {
+            String::new()
+        } else {
+            section("⚠ BREAKING CHANGES", breaking.into_iter())
+        };
+
+        substitute(&format.template, |placeholder| match placeholder {
+            "version" => Some(&self.version),
+            "date" => Some(&self.date),
+            "breaking" => Some(&breaking),
+            "sections" => Some(&sections),
+            _ => None,
+        })
+    }
+}
+
+impl Entry {
+    fn from_record(record: &CommitRecord, ref_patterns: &[Regex]) -> Entry {
+        let (kind, scope, description, marked_breaking) =
+            match ConventionalSubject::parse(&record.subject) {
+                Some(subject) => (
+                    Some(subject.kind.to_lowercase()),
+                    subject.scope,
+                    subject.description,
+                    subject.breaking,
+                ),
+                None => (None, None, record.subject.clone(), false),
+            };
+
+        // the subject can have references too, e.g. the `(#123)` GitHub adds to a squash merge
+        let mut refs: Vec<String> = Vec::new();
+        for text in [&description, &record.body] {
+            for pattern in ref_patterns {
+                for captures in pattern.captures_iter(text) {
+                    let found = captures.get(1).or(captures.get(0)).unwrap().as_str();
+                    if !refs.iter().any(|r| r == found) {
+                        refs.push(found.to_string());
+                    }
+                }
+            }
+        }
+        let description = strip_trailing_refs(&description, &refs);
+
+        let mut breaking = breaking_notes(&record.body);
+        if breaking.is_empty() && marked_breaking {
+            breaking.push(description.clone());
+        }
+
+        Entry {
+            kind,
+            scope,
+            description,
+            breaking,
+            hash: record.hash.clone(),
+            author: record.author.name.clone(),
+            refs,
+        }
+    }
+
+    /// Fill in the placeholders of `format`: `{type}`, `{scope}` (`**scope:** `, if there is one), `{description}`,
+    /// `{hash}`, `{short_hash}`, `{author}` and `{refs}` (` (#12, ABC-123)`, if there are any).
+    fn render(&self, format: &str) -> String {
+        let scope = match &self.scope {
+            Some(scope) => format!("**{scope}:** "),
+            None => String::new(),
+        };
+        let refs = if self.refs.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", self.refs.join(", "))
+        };
+        // the length GitHub abbreviates hashes to
+        let short_hash: String = self.hash.chars().take(7).collect();
+
+        substitute(format, |placeholder| match placeholder {
+            "type" => Some(self.kind.as_deref().unwrap_or_default()),
+            "scope" => Some(&scope),
+            "description" => Some(&self.description),
+            "hash" => Some(&self.hash),
+            "short_hash" => Some(&short_hash),
+            "author" => Some(&self.author),
+            "refs" => Some(&refs),
+            _ => None,
+        })
+    }
+}
+
+impl ChangelogFormat {
+    /// Load the format from the `git-util.changelog.*` settings; `template`, if set, is the path of a template file that
+    /// overrides `git-util.changelog.template`.
+    pub fn from_config(template: Option<&str>) -> Result<ChangelogFormat> {
+        let mut sections = GitUtilConfig::ChangelogSections.get_all()?;
+        if sections.is_empty() {
+            sections = DEFAULT_SECTIONS.iter().map(ToString::to_string).collect();
+        }
+
+        let template = match template.map(str::to_string) {
+            Some(path) => Some(path),
+            None => GitUtilConfig::ChangelogTemplate.get()?,
+        };
+
+        Ok(ChangelogFormat {
+            sections: sections
+                .iter()
+                .map(|section| match section.split_once('=') {
+                    Some((kind, title)) => (kind.trim().to_lowercase(), title.trim().to_string()),
+                    None => (section.trim().to_lowercase(), section.trim().to_string()),
+                })
+                .collect(),
+            entry: GitUtilConfig::ChangelogEntryFormat
+                .get()?
+                .unwrap_or_else(|| DEFAULT_ENTRY_FORMAT.to_string()),
+            template: match template {
+                Some(path) => std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read the changelog template {path}"))?,
+                None => DEFAULT_TEMPLATE.to_string(),
+            },
+        })
+    }
+
+    /// The key of the section `entry` is listed in, if any.
+    fn section_of(&self, entry: &Entry) -> Option<&String> {
+        let has_section = |kind: &str| self.sections.iter().find(|(k, _)| k == kind);
+
+        entry
+            .kind
+            .as_deref()
+            .and_then(has_section)
+            .or_else(|| has_section(OTHER))
+            .map(|(kind, _)| kind)
+    }
+}
+
+/// Replace each `{name}` in `template` with `value(name)`, in a single pass, so placeholders in the substituted text
+/// (e.g. a subject mentioning `{hash}`) are left as they are; unknown placeholders are kept.
+fn substitute<'a>(template: &str, value: impl Fn(&str) -> Option<&'a str>) -> String {
+    let placeholder = Regex::new(r"\{(\w+)\}").expect("placeholder regex should be valid");
+
+    placeholder
+        .replace_all(template, |captures: &regex::Captures| {
+            value(&captures[1]).unwrap_or(&captures[0]).to_string()
+        })
+        .into_owned()
+}
+
+/// A Markdown section: a `###` heading followed by `entries`, one per line.
+fn section(title: &str, entries: impl Iterator<Item = String>) -> String {
+    let mut section = format!("### {title}\n\n");
+    for entry in entries {
+        section.push_str(&entry);
+        section.push('\n');
+    }
+    section.push('\n');
+    section
+}
+
+/// The `BREAKING CHANGE:` (or `BREAKING-CHANGE:`) footers in `body`; each runs until the next blank line or trailer.
+fn breaking_notes(body: &str) -> Vec<String> {
+    let footer = Regex::new(r"^BREAKING[ -]CHANGE: ?(.*)$").expect("footer regex should be valid");
+    let trailer =
+        Regex::new(r"^[A-Za-z0-9][A-Za-z0-9-]*: ").expect("trailer regex should be valid");
+
+    let mut notes: Vec<String> = Vec::new();
+    let mut current: Option<String> = None;
+
+    for line in body.lines() {
+        if let Some(captures) = footer.captures(line) {
+            notes.extend(current.take());
+            current = Some(captures[1].trim().to_string());
+        } else if line.trim().is_empty() || trailer.is_match(line) {
+            notes.extend(current.take());
+        } else if let Some(note) = current.as_mut() {
+            note.push(' ');
+            note.push_str(line.trim());
+        }
+    }
+    notes.extend(current);
+
+    notes.into_iter().filter(|note| !note.is_empty()).collect()
+}
+
+/// `description` without a trailing `(#12)` or `(#12, ABC-123)` that only lists `refs`, which are rendered by `{refs}`.
+fn strip_trailing_refs(description: &str, refs: &[String]) -> String {
+    let listed = description
+        .trim_end()
+        .strip_suffix(')')
+        .and_then(|d| d.rsplit_once('('));
+
+    match listed {
+        Some((before, list))
+            if !list.trim().is_empty()
+                && list
+                    .split(',')
+                    .all(|r| refs.iter().any(|found| found == r.trim())) =>
+        {
+            before.trim_end().to_string()
+        }
+        _ => description.to_string(),
+    }
+}
+
+/// The patterns for issue references: `#123`, and the ticket patterns of the `prepare-commit-msg` hook if any are
+/// configured.
+///
+/// The hook's default pattern isn't used, since commit messages (unlike branch names) often mention things like
+/// `UTF-8` or `SHA-256` that look like ticket IDs.
+fn ref_patterns() -> Result<Vec<Regex>> {
+    [ISSUE_PATTERN.to_string()]
+        .into_iter()
+        .chain(GitUtilConfig::PrepareCommitMsgTicketPatterns.get_all()?)
+        .map(|pattern| {
+            Regex::new(&pattern).with_context(|| {
+                format!(
+                    "Invalid {} \"{}\"",
+                    GitUtilConfig::PrepareCommitMsgTicketPatterns,
+                    pattern
+                )
+            })
+        })
+        .collect()
+}
+
+/// The most recent tag reachable from `HEAD`, if there is one.
+///
+/// `git describe --tags --abbrev=0`
+fn latest_tag() -> Result<Option<String>> {
+    let output = GitCommand::new("describe")
+        .with_default_args(&["--tags", "--abbrev=0"])
+        .capture()?;
+
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
@@ -0,0 +1,310 @@
+use anyhow::{anyhow, Result};
+use nu_ansi_term::Color;
+use std::collections::BTreeMap;
+
+use super::{paint, read_log, RECORD_SEPARATOR};
+use crate::{git::GitCommand, glob::Glob};
+
+/// The `git log` options that `ChangedPath::read` parses: one `%x1e` per commit, followed by its NUL-separated
+/// `--name-status` entries.
+const NAME_STATUS_OPTIONS: [&str; 4] = ["--format=%x1e", "--name-status", "--find-renames", "-z"];
+
+/// The net change to a path across a set of commits.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum NetStatus {
+    Added,
+    Modified,
+    Deleted,
+    /// Renamed, and possibly modified.
+    Renamed,
+    /// Copied from another path, and possibly modified.
+    Copied,
+}
+
+/// A path changed by a set of commits, with its net status.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChangedPath {
+    pub status: NetStatus,
+    /// The path after the commits; for a deletion, the path that was deleted.
+    pub path: String,
+    /// The path before the commits, for renames and copies.
+    pub old_path: Option<String>,
+    /// The number of commits that changed the path (under any of its names).
+    pub commits: usize,
+    /// The path also has staged or unstaged changes.
+    pub uncommitted: bool,
+}
+
+impl NetStatus {
+    fn letter(self) -> char {
+        match self {
+            NetStatus::Added => 'A',
+            NetStatus::Modified => 'M',
+            NetStatus::Deleted => 'D',
+            NetStatus::Renamed => 'R',
+            NetStatus::Copied => 'C',
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            NetStatus::Added => Color::Green,
+            NetStatus::Modified => Color::Yellow,
+            NetStatus::Deleted => Color::Red,
+            NetStatus::Renamed | NetStatus::Copied => Color::Cyan,
+        }
+    }
+}
+
+impl ChangedPath {
+    /// Read the paths changed by the commits selected by `git log OPTIONS ARGS`, followed by the staged and unstaged
+    /// changes if `uncommitted`, merged into one entry per path with its net status; paths that were added and then
+    /// deleted are left out.
+    pub fn read(options: &[&str], args: &[String], uncommitted: bool) -> Result<Vec<ChangedPath>> {
+        let default_args: Vec<&str> = NAME_STATUS_OPTIONS.iter().chain(options).copied().collect();
+        let output = read_log(&default_args, args)?;
+
+        let mut changes: Vec<Vec<(String, Vec<String>)>> = output
+            .split(RECORD_SEPARATOR)
+            .map(parse_name_status)
+            .collect::<Result<_>>()?;
+
+        // `git log` lists the newest commit first
+        changes.reverse();
+
+        let mut paths: BTreeMap<String, ChangedPath> = BTreeMap::new();
+        for commit in changes {
+            for (status, names) in commit {
+                if let Some(changed) =
+                    apply_change(&mut paths, &status, names).and_then(|path| paths.get_mut(&path))
+                {
+                    changed.commits += 1;
+                }
+            }
+        }
+
+        if uncommitted {
+            let diff = GitCommand::new("diff")
+                .with_default_args(&["--name-status", "--find-renames", "-z", "HEAD"])
+                .read_stdout()?;
+
+            for (status, names) in parse_name_status(&diff)? {
+                if let Some(changed) =
+                    apply_change(&mut paths, &status, names).and_then(|path| paths.get_mut(&path))
+                {
+                    changed.uncommitted = true;
+                }
+            }
+        }
+
+        Ok(paths.into_values().collect())
+    }
+}
+
+/// Parse one commit's `--name-status -z` entries into `(status, paths)` pairs.
+fn parse_name_status(record: &str) -> Result<Vec<(String, Vec<String>)>> {
+    let mut fields = record
+        .split('\0')
+        .map(|field| field.trim_start_matches('\n'))
+        .filter(|field| !field.is_empty());
+
+    let mut changes = Vec::new();
+
+    while let Some(status) = fields.next() {
+        // renames and copies are followed by the old and new paths, everything else by one path
+        let count = if status.starts_with(['R', 'C']) { 2 } else { 1 };
+        let names: Vec<String> = fields.by_ref().take(count).map(str::to_string).collect();
+
+        if names.len() != count {
+            return Err(anyhow!(
+                "Failed to parse 'git log --name-status' entry \"{status}\""
+            ));
+        }
+
+        changes.push((status.to_string(), names));
+    }
+
+    Ok(changes)
+}
+
+/// Fold one change into the net changes, which are keyed by current path, returning the key of the changed entry, if
+/// it still exists.
+fn apply_change(
+    paths: &mut BTreeMap<String, ChangedPath>,
+    status: &str,
+    names: Vec<String>,
+) -> Option<String> {
+    let (old, new) = match &names[..] {
+        [old, new] => (Some(old.clone()), new.clone()),
+        _ => (None, names[0].clone()),
+    };
+
+    let changed = |status: NetStatus,
+                   path: &str,
+                   old_path: Option<String>,
+                   previous: Option<&ChangedPath>| {
+        ChangedPath {
+            status,
+            path: path.to_string(),
+            old_path,
+            commits: previous.map_or(0, |p| p.commits),
+            uncommitted: previous.is_some_and(|p| p.uncommitted),
+        }
+    };
+
+    match status.chars().next() {
+        Some('A') => {
+            let previous = paths.remove(&new);
+            let status = match &previous {
+                // deleted and then added again
+                Some(previous) if previous.status == NetStatus::Deleted => NetStatus::Modified,
+                Some(previous) => previous.status,
+                None => NetStatus::Added,
+            };
+            let old_path = previous.as_ref().and_then(|p| p.old_path.clone());
+            paths.insert(
+                new.clone(),
+                changed(status, &new, old_path, previous.as_ref()),
+            );
+            Some(new)
+        }
+        Some('D') => {
+            let previous = paths.remove(&new);
+            match &previous {
+                // added and then deleted
+                Some(ChangedPath {
+                    status: NetStatus::Added | NetStatus::Copied,
+                    ..
+                }) => None,
+                _ => {
+                    // a renamed file is deleted under its original name
+                    let path = previous
+                        .as_ref()
+                        .and_then(|p| p.old_path.clone())
+                        .unwrap_or(new);
+                    paths.insert(
+                        path.clone(),
+                        changed(NetStatus::Deleted, &path, None, previous.as_ref()),
+                    );
+                    Some(path)
+                }
+            }
+        }
+        Some(kind @ ('R' | 'C')) => {
+            let old = old.expect("renames and copies have two paths");
+            let previous = if kind == 'R' {
+                paths.remove(&old)
+            } else {
+                None
+            };
+
+            let (status, old_path) = match &previous {
+                Some(previous) => match previous.status {
+                    NetStatus::Added | NetStatus::Copied => {
+                        (previous.status, previous.old_path.clone())
+                    }
+                    _ => {
+                        let original = previous.old_path.clone().unwrap_or(old);
+                        // renamed back to where it started
+                        if original == new {
+                            (NetStatus::Modified, None)
+                        } else {
+                            (NetStatus::Renamed, Some(original))
+                        }
+                    }
+                },
+                None if kind == 'R' => (NetStatus::Renamed, Some(old)),
+                None => (NetStatus::Copied, Some(old)),
+            };
+
+            paths.insert(
+                new.clone(),
+                changed(status, &new, old_path, previous.as_ref()),
+            );
+            Some(new)
+        }
+        // modifications and type changes
+        _ => {
+            paths
+                .entry(new.clone())
+                .or_insert_with(|| changed(NetStatus::Modified, &new, None, None));
+            Some(new)
+        }
+    }
+}
+
+/// Keep the paths whose current or old path matches one of `globs`; all of them if there are none.
+pub fn filter(paths: Vec<ChangedPath>, globs: &[Glob]) -> Vec<ChangedPath> {
+    if globs.is_empty() {
+        return paths;
+    }
+
+    paths
+        .into_iter()
+        .filter(|changed| {
+            globs.iter().any(|glob| {
+                glob.is_path_match(&changed.path)
+                    || changed
+                        .old_path
+                        .as_ref()
+                        .is_some_and(|old| glob.is_path_match(old))
+            })
+        })
+        .collect()
+}
+
+/// Render `paths` one per line: the status, the number of commits (followed by `*` if there are uncommitted changes)
+/// and the path, with `old → new` for renames and copies. With `tree`, the paths are grouped under their directories.
+pub fn render(paths: &[ChangedPath], tree: bool, colored: bool) -> String {
+    let commits_width = paths
+        .iter()
+        .map(|p| p.commits.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    let mut open_directories: Vec<&str> = Vec::new();
+
+    for changed in paths {
+        let (directories, name) = if tree {
+            match changed.path.rsplit_once('/') {
+                Some((directory, name)) => (directory.split('/').collect(), name),
+                None => (Vec::new(), changed.path.as_str()),
+            }
+        } else {
+            (Vec::new(), changed.path.as_str())
+        };
+
+        if tree {
+            let shared = open_directories
+                .iter()
+                .zip(&directories)
+                .take_while(|(a, b)| a == b)
+                .count();
+
+            for (depth, directory) in directories.iter().enumerate().skip(shared) {
+                output.push_str(&"  ".repeat(depth));
+                output.push_str(&paint(&format!("{directory}/"), Color::Blue, colored));
+                output.push('\n');
+            }
+            open_directories = directories.clone();
+        }
+
+        let status = changed.status.letter().to_string();
+        output.push_str(&"  ".repeat(directories.len()));
+        output.push_str(&paint(&status, changed.status.color(), colored));
+        output.push_str(&format!(
+            " {:>commits_width$}{} ",
+            changed.commits,
+            if changed.uncommitted { "*" } else { " " }
+        ));
+
+        if let Some(old) = &changed.old_path {
+            output.push_str(&format!("{old} → "));
+        }
+        output.push_str(name);
+        output.push('\n');
+    }
+
+    output
+}
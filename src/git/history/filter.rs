@@ -0,0 +1,181 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use regex::Regex;
+
+use crate::git::{config, Git, GitCommand};
+
+/// Filters for the commits listed by the log-style subcommands, translated into `git log` arguments.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct LogFilter {
+    /// Only commits authored with the current `user.email`.
+    pub mine: bool,
+    /// Only commits whose author matches this pattern.
+    pub author: Option<String>,
+    /// A date or a duration such as `2w` or `3 days`, which is taken as that long ago.
+    pub since: Option<String>,
+    /// A date or a duration such as `2w` or `3 days`, which is taken as that long ago.
+    pub until: Option<String>,
+    /// Only commits that change these paths.
+    pub paths: Vec<String>,
+    /// Only commits whose message matches this pattern.
+    pub grep: Option<String>,
+    pub no_merges: bool,
+    /// Only commits since the merge-base of `HEAD` and the default branch.
+    pub branch_only: bool,
+}
+
+impl LogFilter {
+    /// The `git log` arguments for `self`, followed by `args`; the paths are added after `args`, since they must follow
+    /// any revisions.
+    pub fn apply(&self, args: &[String]) -> Result<Vec<String>> {
+        let mut filtered: Vec<String> = Vec::new();
+
+        if self.mine {
+            let email = config::get_all("user.email", None)?
+                .pop()
+                .ok_or_else(|| anyhow!("--mine needs user.email to be set"))?;
+            filtered.push(format!("--author=<{}>", escape_log_pattern(&email)));
+        }
+
+        if let Some(author) = &self.author {
+            filtered.push(format!("--author={author}"));
+        }
+
+        if let Some(since) = &self.since {
+            filtered.push(format!("--since={}", approxidate(since)));
+        }
+
+        if let Some(until) = &self.until {
+            filtered.push(format!("--until={}", approxidate(until)));
+        }
+
+        if let Some(grep) = &self.grep {
+            filtered.push(format!("--grep={grep}"));
+        }
+
+        if self.no_merges {
+            filtered.push("--no-merges".to_string());
+        }
+
+        if self.branch_only {
+            filtered.push(format!("{}..HEAD", merge_base(&default_branch()?)?));
+        }
+
+        filtered.extend_from_slice(args);
+
+        if !self.paths.is_empty() {
+            if !args.iter().any(|arg| arg == "--") {
+                filtered.push("--".to_string());
+            }
+            filtered.extend_from_slice(&self.paths);
+        }
+
+        Ok(filtered)
+    }
+}
+
+/// Escape `text` so a `git log` pattern (e.g. `--author`) matches it literally.
+///
+/// Whether the pattern is a basic, extended or Perl regex depends on `-E`, `-P` and `grep.patternType`, and
+/// `regex::escape` won't do for a basic one, where e.g. `\+` means "one or more"; a bracket expression like `[+]` means
+/// the same in all of them.
+fn escape_log_pattern(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '^' => r"\^".to_string(),
+            '.' | '*' | '+' | '?' | '(' | ')' | '{' | '}' | '|' | '$' | '[' | ']' | '\\' => {
+                format!("[{c}]")
+            }
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// The commit the current branch was started from: its merge-base with `base`, else with its upstream, unless that is
+/// the same branch on a remote (which would only give the last push), else with the default branch.
+pub fn branch_point(base: Option<&str>) -> Result<String> {
+    let base = match base {
+        Some(base) => base.to_string(),
+        None => match (
+            Git::upstream()?.map(|upstream| Git::short_name(&upstream).to_string()),
+            Git::current_branch()?,
+        ) {
+            (Some(upstream), Some(branch)) if !upstream.ends_with(&format!("/{branch}")) => {
+                upstream
+            }
+            (Some(upstream), None) => upstream,
+            _ => default_branch()?,
+        },
+    };
+
+    merge_base(&base)
+}
+
+/// `git merge-base HEAD BASE`
+fn merge_base(base: &str) -> Result<String> {
+    let merge_base = GitCommand::new("merge-base")
+        .with_default_args(&["HEAD", base])
+        .read_stdout()
+        .with_context(|| format!("Failed to find the merge-base of HEAD and {base}"))?;
+    debug!("merge-base with {base} is {}", merge_base.trim());
+
+    Ok(merge_base.trim().to_string())
+}
+
+/// Turn a duration such as `2w`, `36h` or `3 days` into `2 weeks ago`, etc., for Git's date parser; anything else (e.g.
+/// `2024-05-01` or `yesterday`) is passed through as it is.
+fn approxidate(value: &str) -> String {
+    let duration = Regex::new(r"^(\d+)\s*([a-z]+?)s?$").expect("duration regex should be valid");
+
+    let Some(captures) = duration.captures(value.trim()) else {
+        return value.to_string();
+    };
+
+    let unit = match &captures[2] {
+        "s" | "sec" | "second" => "seconds",
+        "m" | "min" | "minute" => "minutes",
+        "h" | "hr" | "hour" => "hours",
+        "d" | "day" => "days",
+        "w" | "wk" | "week" => "weeks",
+        "mo" | "month" => "months",
+        "y" | "yr" | "year" => "years",
+        _ => return value.to_string(),
+    };
+
+    format!("{} {unit} ago", &captures[1])
+}
+
+/// The remote's default branch (`origin/HEAD`) if it is known, else the local `init.defaultBranch`, `main` or `master`.
+fn default_branch() -> Result<String> {
+    let remote_head = GitCommand::new("symbolic-ref")
+        .with_default_args(&["--quiet", "--short", "refs/remotes/origin/HEAD"])
+        .capture()?;
+
+    if remote_head.status.success() {
+        return Ok(String::from_utf8_lossy(&remote_head.stdout)
+            .trim()
+            .to_string());
+    }
+
+    let configured = config::get_all("init.defaultBranch", None)?.pop();
+
+    for branch in configured
+        .iter()
+        .map(String::as_str)
+        .chain(["main", "master"])
+    {
+        let exists = GitCommand::new("rev-parse")
+            .with_default_args(&["--verify", "--quiet", &format!("refs/heads/{branch}")])
+            .capture()?
+            .status
+            .success();
+
+        if exists {
+            return Ok(branch.to_string());
+        }
+    }
+
+    Err(anyhow!(
+        "Couldn't find the default branch; set it with `git remote set-head origin --auto`"
+    ))
+}
@@ -0,0 +1,343 @@
+use nu_ansi_term::{Color, Style};
+
+use super::{paint, tracking::Tracking, Commit, OneLineColumns};
+use crate::git::Git;
+
+/// Runs of more linear commits than this are collapsed, keeping the first and last commit of the run.
+const MAX_LINEAR_RUN: usize = 5;
+
+/// The commits to highlight in the graph.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Highlights {
+    /// The hash of `HEAD`, unless the branch is unborn.
+    pub head: Option<String>,
+    /// The short name (e.g. `origin/main`) and hash of the current branch's upstream, if it has one.
+    pub upstream: Option<(String, String)>,
+}
+
+impl Highlights {
+    /// Resolve `HEAD` and `@{upstream}`; either is left unset if it can't be resolved.
+    pub fn read() -> Highlights {
+        let upstream = Git::upstream().ok().flatten().and_then(|upstream| {
+            let hash = Git::rev_parse(&upstream).ok().flatten()?;
+            Some((Git::short_name(&upstream).to_string(), hash))
+        });
+
+        Highlights {
+            head: Git::rev_parse("HEAD").ok().flatten(),
+            upstream,
+        }
+    }
+
+    fn is_head(&self, hash: &str) -> bool {
+        self.head.as_deref() == Some(hash)
+    }
+
+    fn is_upstream(&self, hash: &str) -> bool {
+        self.upstream.as_ref().is_some_and(|(_, h)| h == hash)
+    }
+}
+
+/// A line of the graph; each cell is a lane, drawn as a character followed by a space.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Row {
+    /// `commits[index]`, drawn in lane `column`.
+    Commit {
+        cells: Vec<char>,
+        column: usize,
+        index: usize,
+    },
+    /// Edges between lanes, e.g. `|\` below a merge or `|/` above a commit with several children; drawn between the
+    /// cells rather than in them.
+    Edges(Vec<char>),
+    /// `count` linear commits that were left out, drawn in lane `column`.
+    Collapsed {
+        cells: Vec<char>,
+        column: usize,
+        count: usize,
+    },
+}
+
+/// Render `commits` one per line with their ancestry drawn in lanes to the left, as `git log --graph` does, but keeping
+/// the columns of `render_one_line` aligned.
+///
+/// `commits` must list children before their parents (e.g. `--date-order`). Merges are drawn as `M`, `HEAD` as `@` and
+/// the upstream as `^`; runs of more than `MAX_LINEAR_RUN` commits without branching, merging or refs are collapsed. With
+/// `tracking`, the columns start with marks for the commits that haven't been pushed or pulled.
+pub fn render(
+    commits: &[Commit],
+    highlights: &Highlights,
+    tracking: Option<&Tracking>,
+    colored: bool,
+    width: Option<usize>,
+) -> String {
+    let rows = collapse_linear_runs(layout(commits), commits, highlights);
+
+    let graph_width = rows
+        .iter()
+        .map(|row| match row {
+            Row::Commit { cells, .. } | Row::Collapsed { cells, .. } => cells.len() * 2,
+            Row::Edges(edges) => edges.len(),
+        })
+        .max()
+        .unwrap_or(0);
+
+    let columns = OneLineColumns::new(commits, colored)
+        .emphasize_ref(highlights.upstream.as_ref().map(|(name, _)| name.as_str()))
+        .mark_tracking(tracking);
+    let line_width = width.map(|w| w.saturating_sub(graph_width));
+
+    let mut output = String::new();
+
+    for row in &rows {
+        match row {
+            Row::Commit {
+                cells,
+                column,
+                index,
+            } => {
+                let commit = &commits[*index];
+                let (marker, style) = if highlights.is_head(&commit.hash) {
+                    ('@', Color::Green.bold())
+                } else if highlights.is_upstream(&commit.hash) {
+                    ('^', Color::Red.bold())
+                } else if commit.parents.len() > 1 {
+                    ('M', Color::Purple.normal())
+                } else {
+                    ('*', Style::new())
+                };
+
+                output.push_str(&draw_cells(
+                    cells,
+                    *column,
+                    marker,
+                    style,
+                    graph_width,
+                    colored,
+                ));
+                output.push_str(&columns.line(*index, line_width));
+            }
+            Row::Edges(edges) => {
+                let edges: String = edges.iter().collect();
+                output.push_str(edges.trim_end());
+            }
+            Row::Collapsed {
+                cells,
+                column,
+                count,
+            } => {
+                output.push_str(&draw_cells(
+                    cells,
+                    *column,
+                    ':',
+                    Style::new(),
+                    graph_width,
+                    colored,
+                ));
+                output.push_str(&paint(
+                    &format!("… {count} more commits"),
+                    Color::DarkGray,
+                    colored,
+                ));
+            }
+        }
+
+        output.push('\n');
+    }
+
+    output
+}
+
+/// Assign each commit a lane, adding `Edges` rows where lanes fork or join.
+fn layout(commits: &[Commit]) -> Vec<Row> {
+    // the hash each lane is waiting for, i.e. the parent of the last commit drawn in it
+    let mut lanes: Vec<Option<&str>> = Vec::new();
+    let mut rows: Vec<Row> = Vec::new();
+
+    for (index, commit) in commits.iter().enumerate() {
+        let hash = commit.hash.as_str();
+
+        let column = lanes
+            .iter()
+            .position(|lane| *lane == Some(hash))
+            .unwrap_or_else(|| free_lane(&mut lanes));
+
+        // other children of this commit were drawn in their own lanes, which end here
+        let joining: Vec<usize> = (0..lanes.len())
+            .filter(|&i| i != column && lanes[i] == Some(hash))
+            .collect();
+
+        if !joining.is_empty() {
+            rows.push(Row::Edges(edges(&lanes, column, &joining, ['/', '\\'])));
+
+            for i in joining {
+                lanes[i] = None;
+            }
+        }
+
+        rows.push(Row::Commit {
+            cells: cells(&lanes),
+            column,
+            index,
+        });
+
+        lanes[column] = commit.parents.first().map(String::as_str);
+
+        // a merge's other parents continue in the lanes already waiting for them, or in new ones
+        let forks: Vec<usize> = commit.parents[1.min(commit.parents.len())..]
+            .iter()
+            .map(|parent| {
+                lanes
+                    .iter()
+                    .position(|lane| *lane == Some(parent.as_str()))
+                    .unwrap_or_else(|| {
+                        let lane = free_lane(&mut lanes);
+                        lanes[lane] = Some(parent.as_str());
+                        lane
+                    })
+            })
+            .collect();
+
+        if !forks.is_empty() {
+            rows.push(Row::Edges(edges(&lanes, column, &forks, ['\\', '/'])));
+        }
+
+        while lanes.last().is_some_and(Option::is_none) {
+            lanes.pop();
+        }
+    }
+
+    rows
+}
+
+/// Replace the middle of each run of more than `MAX_LINEAR_RUN` linear commits with a `Collapsed` row.
+///
+/// A commit is linear if it has a single parent and no refs, isn't highlighted, and is drawn in the same lane as the
+/// commit before it with no edges in between.
+fn collapse_linear_runs(rows: Vec<Row>, commits: &[Commit], highlights: &Highlights) -> Vec<Row> {
+    let is_linear = |row: &Row| match row {
+        Row::Commit { index, .. } => {
+            let commit = &commits[*index];
+            commit.parents.len() == 1
+                && commit.refs.is_empty()
+                && !highlights.is_head(&commit.hash)
+                && !highlights.is_upstream(&commit.hash)
+        }
+        _ => false,
+    };
+
+    let mut collapsed: Vec<Row> = Vec::new();
+    let mut run: Vec<Row> = Vec::new();
+
+    let flush = |run: &mut Vec<Row>, collapsed: &mut Vec<Row>| {
+        if run.len() > MAX_LINEAR_RUN {
+            let (first, last) = (run.remove(0), run.pop());
+            let Row::Commit { cells, column, .. } = &first else {
+                unreachable!("only commit rows are linear");
+            };
+            let hidden = Row::Collapsed {
+                cells: cells.clone(),
+                column: *column,
+                count: run.len(),
+            };
+            collapsed.extend([first, hidden]);
+            collapsed.extend(last);
+        } else {
+            collapsed.append(run);
+        }
+        run.clear();
+    };
+
+    for row in rows {
+        let continues_run = match (run.last(), &row) {
+            (
+                Some(Row::Commit {
+                    cells: previous_cells,
+                    column: previous_column,
+                    ..
+                }),
+                Row::Commit { cells, column, .. },
+            ) => previous_column == column && previous_cells == cells,
+            _ => true,
+        };
+
+        if !is_linear(&row) || !continues_run {
+            flush(&mut run, &mut collapsed);
+        }
+
+        if is_linear(&row) {
+            run.push(row);
+        } else {
+            collapsed.push(row);
+        }
+    }
+    flush(&mut run, &mut collapsed);
+
+    collapsed
+}
+
+/// The index of the first unused lane, adding one if they are all in use.
+fn free_lane(lanes: &mut Vec<Option<&str>>) -> usize {
+    lanes.iter().position(Option::is_none).unwrap_or_else(|| {
+        lanes.push(None);
+        lanes.len() - 1
+    })
+}
+
+/// A row of edges from lane `column` to each of `others`, drawn in the gap next to the other lane: `right` for lanes to
+/// the right of `column` and `left` for those to its left. Lanes in use are drawn as `|`.
+fn edges(
+    lanes: &[Option<&str>],
+    column: usize,
+    others: &[usize],
+    [right, left]: [char; 2],
+) -> Vec<char> {
+    let mut edges: Vec<char> = (0..lanes.len())
+        .flat_map(|i| {
+            let in_use = i == column || (lanes[i].is_some() && !others.contains(&i));
+            [if in_use { '|' } else { ' ' }, ' ']
+        })
+        .collect();
+
+    for &other in others {
+        if other > column {
+            edges[other * 2 - 1] = right;
+        } else {
+            edges[other * 2 + 1] = left;
+        }
+    }
+
+    edges
+}
+
+/// `|` for each lane in use, else a space.
+fn cells(lanes: &[Option<&str>]) -> Vec<char> {
+    lanes
+        .iter()
+        .map(|lane| if lane.is_some() { '|' } else { ' ' })
+        .collect()
+}
+
+/// Draw `cells` with `marker` in lane `column`, padded to `graph_width`.
+fn draw_cells(
+    cells: &[char],
+    column: usize,
+    marker: char,
+    style: Style,
+    graph_width: usize,
+    colored: bool,
+) -> String {
+    let mut drawn = String::new();
+
+    for (i, cell) in cells.iter().enumerate() {
+        if i == column {
+            drawn.push_str(&paint(&marker.to_string(), style, colored));
+        } else {
+            drawn.push(*cell);
+        }
+        drawn.push(' ');
+    }
+
+    drawn.push_str(&" ".repeat(graph_width.saturating_sub(cells.len() * 2)));
+    drawn
+}
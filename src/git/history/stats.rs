@@ -0,0 +1,308 @@
+use nu_ansi_term::{Color, Style};
+use std::collections::HashMap;
+
+use super::{paint, CommitRecord, Identity};
+use crate::json::Json;
+
+/// The lines changed in a file or directory, or by an author, across a set of commits.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Churn {
+    /// The number of commits that changed it.
+    pub commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    /// The number of changes to binary files, which have no line counts.
+    pub binary_changes: usize,
+}
+
+/// Churn aggregated by file, directory and author; each list is sorted with the largest first.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ChurnStats {
+    pub total: Churn,
+    /// By lines changed.
+    pub files: Vec<(String, Churn)>,
+    /// By lines changed; a file's directory is its parent, or `.` for files at the top level.
+    pub directories: Vec<(String, Churn)>,
+    /// By number of commits.
+    pub authors: Vec<(Identity, Churn)>,
+}
+
+impl Churn {
+    fn lines(&self) -> usize {
+        self.insertions + self.deletions
+    }
+
+    /// Add the lines a commit changed in one file; a file without line counts is a binary change.
+    fn add(&mut self, insertions: Option<usize>, deletions: Option<usize>) {
+        match (insertions, deletions) {
+            (Some(insertions), Some(deletions)) => {
+                self.insertions += insertions;
+                self.deletions += deletions;
+            }
+            _ => self.binary_changes += 1,
+        }
+    }
+
+    /// A JSON object with `names` followed by the counts.
+    fn to_json<const N: usize>(&self, names: [(&str, Json); N]) -> Json {
+        let counts = [
+            ("commits", self.commits.into()),
+            ("insertions", self.insertions.into()),
+            ("deletions", self.deletions.into()),
+            ("binary_changes", self.binary_changes.into()),
+        ];
+
+        Json::Object(
+            names
+                .into_iter()
+                .chain(counts)
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+}
+
+impl ChurnStats {
+    pub fn from_records(records: &[CommitRecord]) -> ChurnStats {
+        let mut total = Churn::default();
+        let mut files: HashMap<&str, Churn> = HashMap::new();
+        let mut directories: HashMap<&str, Churn> = HashMap::new();
+        let mut authors: HashMap<(&str, &str), (Identity, Churn)> = HashMap::new();
+
+        for record in records {
+            total.commits += 1;
+
+            let (_, author) = authors
+                .entry((&record.author.name, &record.author.email))
+                .or_insert_with(|| (record.author.clone(), Churn::default()));
+            author.commits += 1;
+
+            let mut directories_in_commit: Vec<&str> = Vec::new();
+
+            for file in &record.files {
+                total.add(file.insertions, file.deletions);
+                author.add(file.insertions, file.deletions);
+
+                let churn = files.entry(&file.path).or_default();
+                churn.commits += 1;
+                churn.add(file.insertions, file.deletions);
+
+                let directory = file.path.rsplit_once('/').map_or(".", |(dir, _)| dir);
+                let churn = directories.entry(directory).or_default();
+                churn.add(file.insertions, file.deletions);
+                if !directories_in_commit.contains(&directory) {
+                    churn.commits += 1;
+                    directories_in_commit.push(directory);
+                }
+            }
+        }
+
+        ChurnStats {
+            total,
+            files: sorted_by_lines(files),
+            directories: sorted_by_lines(directories),
+            authors: {
+                let mut authors: Vec<(Identity, Churn)> = authors.into_values().collect();
+                authors.sort_by(|(a, a_churn), (b, b_churn)| {
+                    b_churn
+                        .commits
+                        .cmp(&a_churn.commits)
+                        .then(b_churn.lines().cmp(&a_churn.lines()))
+                        .then(a.name.cmp(&b.name))
+                });
+                authors
+            },
+        }
+    }
+
+    /// Render the stats as tables of at most `top` rows: files and directories by lines changed, files by number of
+    /// commits, and authors.
+    pub fn render_tables(&self, top: usize, colored: bool) -> String {
+        let mut hotspots = self.files.clone();
+        hotspots.sort_by(|(a, a_churn), (b, b_churn)| {
+            b_churn
+                .commits
+                .cmp(&a_churn.commits)
+                .then(b_churn.lines().cmp(&a_churn.lines()))
+                .then(a.cmp(b))
+        });
+
+        let binary: Vec<&(String, Churn)> = self
+            .files
+            .iter()
+            .filter(|(_, churn)| churn.binary_changes > 0)
+            .collect();
+
+        let path_rows = |entries: &[&(String, Churn)]| -> Vec<Vec<String>> {
+            entries
+                .iter()
+                .take(top)
+                .map(|(path, churn)| churn_row(churn, path))
+                .collect()
+        };
+
+        let mut output = format!(
+            "{} commits, {} files changed, {} insertions(+), {} deletions(-), {} binary changes\n",
+            self.total.commits,
+            self.files.len(),
+            self.total.insertions,
+            self.total.deletions,
+            self.total.binary_changes,
+        );
+
+        let sections = [
+            (
+                "Files by lines changed",
+                "path",
+                path_rows(&self.files.iter().collect::<Vec<_>>()),
+            ),
+            (
+                "Directories by lines changed",
+                "directory",
+                path_rows(&self.directories.iter().collect::<Vec<_>>()),
+            ),
+            (
+                "Most frequently changed files",
+                "path",
+                path_rows(&hotspots.iter().collect::<Vec<_>>()),
+            ),
+            ("Binary files", "path", path_rows(&binary)),
+            (
+                "Authors",
+                "author",
+                self.authors
+                    .iter()
+                    .take(top)
+                    .map(|(author, churn)| {
+                        churn_row(churn, &format!("{} <{}>", author.name, author.email))
+                    })
+                    .collect(),
+            ),
+        ];
+
+        for (title, name_header, rows) in sections {
+            if rows.is_empty() {
+                continue;
+            }
+
+            output.push('\n');
+            output.push_str(&paint(title, Style::new().bold(), colored));
+            output.push('\n');
+            output.push_str(&table(&rows, name_header, colored));
+        }
+
+        output
+    }
+
+    pub fn to_json(&self) -> Json {
+        let paths = |entries: &[(String, Churn)]| {
+            Json::Array(
+                entries
+                    .iter()
+                    .map(|(path, churn)| churn.to_json([("path", path.as_str().into())]))
+                    .collect(),
+            )
+        };
+
+        Json::object([
+            (
+                "total",
+                self.total.to_json([("files", self.files.len().into())]),
+            ),
+            ("files", paths(&self.files)),
+            ("directories", paths(&self.directories)),
+            (
+                "authors",
+                Json::Array(
+                    self.authors
+                        .iter()
+                        .map(|(author, churn)| {
+                            churn.to_json([
+                                ("name", author.name.as_str().into()),
+                                ("email", author.email.as_str().into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+}
+
+/// Sort by lines changed, then number of commits, then name.
+fn sorted_by_lines(entries: HashMap<&str, Churn>) -> Vec<(String, Churn)> {
+    let mut sorted: Vec<(String, Churn)> = entries
+        .into_iter()
+        .map(|(name, churn)| (name.to_string(), churn))
+        .collect();
+
+    sorted.sort_by(|(a, a_churn), (b, b_churn)| {
+        b_churn
+            .lines()
+            .cmp(&a_churn.lines())
+            .then(b_churn.commits.cmp(&a_churn.commits))
+            .then(a.cmp(b))
+    });
+
+    sorted
+}
+
+/// The commits, insertions, deletions and binary changes columns, followed by `name`.
+fn churn_row(churn: &Churn, name: &str) -> Vec<String> {
+    vec![
+        churn.commits.to_string(),
+        format!("+{}", churn.insertions),
+        format!("-{}", churn.deletions),
+        match churn.binary_changes {
+            0 => String::new(),
+            changes => format!("{changes} bin"),
+        },
+        name.to_string(),
+    ]
+}
+
+/// Lay out `rows` of `churn_row` columns under a header, with `name_header` over the names, right-aligning the counts.
+fn table(rows: &[Vec<String>], name_header: &str, colored: bool) -> String {
+    const HEADERS: [&str; 4] = ["commits", "added", "removed", "binary"];
+
+    // the binary column is left out if nothing in the table is binary
+    let columns: Vec<usize> = (0..4)
+        .filter(|&i| rows.iter().any(|row| !row[i].is_empty()))
+        .collect();
+    let widths: Vec<usize> = (0..4)
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].len())
+                .chain([HEADERS[i].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let styles = [
+        Style::new(),
+        Color::Green.normal(),
+        Color::Red.normal(),
+        Color::Yellow.normal(),
+    ];
+
+    let mut header = String::from("  ");
+    for &i in &columns {
+        header.push_str(&format!("{:>1$}  ", HEADERS[i], widths[i]));
+    }
+    header.push_str(name_header);
+
+    let mut output = format!("{}\n", paint(&header, Style::new().dimmed(), colored));
+
+    for row in rows {
+        output.push_str("  ");
+        for &i in &columns {
+            let cell = format!("{:>1$}", row[i], widths[i]);
+            output.push_str(&paint(&cell, styles[i], colored));
+            output.push_str("  ");
+        }
+        output.push_str(&row[4]);
+        output.push('\n');
+    }
+
+    output
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use nu_ansi_term::Color;
+use std::collections::HashSet;
+
+use super::paint;
+use crate::git::{Git, GitCommand};
+
+/// How the current branch differs from its upstream.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Tracking {
+    /// The short name of the upstream, e.g. `origin/main`.
+    pub upstream: String,
+    /// The hashes of the local commits that aren't on the upstream, i.e. haven't been pushed.
+    pub ahead: HashSet<String>,
+    /// The hashes of the upstream commits that aren't on the current branch, i.e. haven't been pulled.
+    pub behind: HashSet<String>,
+}
+
+impl Tracking {
+    /// Compare `HEAD` with `@{upstream}`, or `None` if the current branch has no upstream (or `HEAD` is detached).
+    pub fn read() -> Result<Option<Tracking>> {
+        let Some(upstream) = Git::upstream()? else {
+            return Ok(None);
+        };
+
+        // `<` marks the commits only on the left side, `>` those only on the right
+        let commits = GitCommand::new("rev-list")
+            .with_default_args(&["--left-right", "HEAD...@{upstream}"])
+            .read_stdout()?;
+
+        let mut tracking = Tracking {
+            upstream: Git::short_name(&upstream).to_string(),
+            ..Tracking::default()
+        };
+
+        for line in commits.lines() {
+            if let Some(hash) = line.strip_prefix('<') {
+                tracking.ahead.insert(hash.to_string());
+            } else if let Some(hash) = line.strip_prefix('>') {
+                tracking.behind.insert(hash.to_string());
+            }
+        }
+
+        Ok(Some(tracking))
+    }
+
+    /// A summary like `git status -sb`'s, e.g. `origin/main: ahead 2, behind 1` or `origin/main: up to date`.
+    pub fn header(&self, colored: bool) -> String {
+        let upstream = paint(&self.upstream, Color::Red, colored);
+
+        let counts: Vec<String> = [
+            (self.ahead.len(), "ahead", Color::Green),
+            (self.behind.len(), "behind", Color::Red),
+        ]
+        .into_iter()
+        .filter(|(count, ..)| *count > 0)
+        .map(|(count, direction, color)| paint(&format!("{direction} {count}"), color, colored))
+        .collect();
+
+        if counts.is_empty() {
+            format!("{upstream}: up to date")
+        } else {
+            format!("{upstream}: {}", counts.join(", "))
+        }
+    }
+
+    /// `↑` for a commit that hasn't been pushed and `↓` for one that hasn't been pulled, with its color.
+    pub fn mark(&self, hash: &str) -> Option<(char, Color)> {
+        if self.ahead.contains(hash) {
+            Some(('↑', Color::Green))
+        } else if self.behind.contains(hash) {
+            Some(('↓', Color::Red))
+        } else {
+            None
+        }
+    }
+}
@@ -1 +1,175 @@
+use anyhow::anyhow;
+use clap::ValueEnum;
+use std::fmt::Display;
+
+use crate::{
+    git::{GitCommandResult, GitResult},
+    print::Print,
+};
+
+mod blobs;
+mod changed_files;
+mod checks;
+pub mod commit_checks;
+pub mod commit_msg;
+pub mod email_policy;
+mod handoff;
+pub mod install;
+mod linters;
+mod paths;
 pub mod pre_commit;
+pub mod pre_push;
+pub mod prepare_commit_msg;
+pub mod report;
+mod signing;
+mod text;
+
+/// The Git hooks that **git-util** implements.
+#[derive(ValueEnum, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Hook {
+    CommitMsg,
+    PreCommit,
+    PrePush,
+    PrepareCommitMsg,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 4] = [
+        Hook::CommitMsg,
+        Hook::PreCommit,
+        Hook::PrePush,
+        Hook::PrepareCommitMsg,
+    ];
+}
+
+impl Display for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hook::CommitMsg => write!(f, "commit-msg"),
+            Hook::PreCommit => write!(f, "pre-commit"),
+            Hook::PrePush => write!(f, "pre-push"),
+            Hook::PrepareCommitMsg => write!(f, "prepare-commit-msg"),
+        }
+    }
+}
+
+/// How serious a finding is; only errors fail a hook.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Severity {
+    Error,
+    /// Something the hook did or noticed that doesn't need fixing, e.g. a file that a linter fixed and re-staged.
+    Note,
+}
+
+/// A rule violation found by a hook.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct Finding {
+    /// Identifies the rule that was violated, e.g. `commit-msg/subject-length`.
+    pub rule: &'static str,
+    pub severity: Severity,
+    /// The commit the violation was found in, if it wasn't found in the index or a commit message file.
+    pub commit: Option<String>,
+    /// The file the violation was found in, if any.
+    pub file: Option<String>,
+    /// The 1-based line number of the violation within `file`, if any.
+    pub line: Option<usize>,
+    /// The 1-based column number (in characters) of the violation within `line`, if any.
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+impl Finding {
+    pub fn new(rule: &'static str, message: String) -> Finding {
+        Finding {
+            rule,
+            severity: Severity::Error,
+            commit: None,
+            file: None,
+            line: None,
+            column: None,
+            message,
+        }
+    }
+
+    /// A finding that is reported, but doesn't fail the hook.
+    pub fn note(rule: &'static str, message: String) -> Finding {
+        Finding {
+            severity: Severity::Note,
+            ..Finding::new(rule, message)
+        }
+    }
+
+    pub fn at(self, file: &str, line: Option<usize>) -> Finding {
+        Finding {
+            file: Some(file.to_string()),
+            line,
+            ..self
+        }
+    }
+
+    pub fn at_column(self, column: usize) -> Finding {
+        Finding {
+            column: Some(column),
+            ..self
+        }
+    }
+
+    pub fn in_commit(self, commit: &str) -> Finding {
+        Finding {
+            commit: Some(commit.to_string()),
+            ..self
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location: Vec<String> = [
+            self.commit.clone(),
+            self.file.clone(),
+            self.line.map(|line| line.to_string()),
+            self.column.map(|column| column.to_string()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if !location.is_empty() {
+            write!(f, "{}: ", location.join(":"))?;
+        }
+        if self.severity != Severity::Error {
+            write!(f, "{}: ", self.severity)?;
+        }
+        write!(f, "{} [{}]", self.message, self.rule)
+    }
+}
+
+/// Print each of `findings` to `stderr`, write the `--report` file if one was requested, and fail if there are any
+/// errors.
+fn report(hook: &str, findings: &[Finding]) -> GitResult {
+    report::write(hook, findings)?;
+
+    for finding in findings {
+        Print::stderr_purple(&finding.to_string());
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+
+    if errors == 0 {
+        return Ok(GitCommandResult::Success);
+    }
+
+    Err(anyhow!("{} hook failed with {} problem(s)", hook, errors))
+}
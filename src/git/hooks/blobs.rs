@@ -0,0 +1,143 @@
+use anyhow::Result;
+
+use super::{changed_files::ChangedFile, Finding};
+use crate::{git::config::GitUtilConfig, glob::Glob};
+
+/// Extensions of archives, which usually belong in a package registry or release rather than in the repository.
+const ARCHIVE_EXTENSIONS: [&str; 13] = [
+    "7z", "bz2", "gz", "jar", "rar", "tar", "tbz2", "tgz", "txz", "war", "xz", "zip", "zst",
+];
+
+/// Extensions of compiled executables and libraries.
+const EXECUTABLE_EXTENSIONS: [&str; 8] = ["a", "bin", "dll", "dylib", "exe", "lib", "o", "so"];
+
+/// Limits on the files being committed; see `GitUtilConfig` for the corresponding config keys.
+#[derive(Debug, Clone)]
+pub struct BlobPolicy {
+    max_file_size: Option<u64>,
+    block_binary: bool,
+    binary_allowed_paths: Vec<Glob>,
+    block_new_artifacts: bool,
+}
+
+impl BlobPolicy {
+    /// Load the policy from the `git-util.pre-commit.*` config settings.
+    pub fn from_config() -> Result<BlobPolicy> {
+        Ok(BlobPolicy {
+            max_file_size: GitUtilConfig::PreCommitMaxFileSize
+                .get_usize()?
+                .map(|size| size as u64),
+            block_binary: GitUtilConfig::PreCommitBlockBinary
+                .get_bool()?
+                .unwrap_or(false),
+            binary_allowed_paths: Glob::new_all(
+                &GitUtilConfig::PreCommitBinaryAllowedPaths.get_all()?,
+            )?,
+            block_new_artifacts: GitUtilConfig::PreCommitBlockNewArtifacts
+                .get_bool()?
+                .unwrap_or(false),
+        })
+    }
+
+    /// `false` if none of the checks are configured, so the staged files don't need to be inspected.
+    pub fn is_enabled(&self) -> bool {
+        self.max_file_size.is_some() || self.block_binary || self.block_new_artifacts
+    }
+
+    pub fn check(&self, files: &[ChangedFile]) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = Vec::new();
+
+        for file in files {
+            if let Some(max) = self.max_file_size {
+                if file.size > max {
+                    findings.push(
+                        Finding::new(
+                            "large-file",
+                            format!(
+                                "File is {}; the maximum is {}",
+                                human_size(file.size),
+                                human_size(max)
+                            ),
+                        )
+                        .at(&file.path, None),
+                    );
+                }
+            }
+
+            if self.block_binary
+                && file.binary
+                && !self
+                    .binary_allowed_paths
+                    .iter()
+                    .any(|g| g.is_path_match(&file.path))
+            {
+                findings.push(
+                    Finding::new(
+                        "binary-file",
+                        format!(
+                            "Binary file ({}) is not in an allowed path ({})",
+                            human_size(file.size),
+                            GitUtilConfig::PreCommitBinaryAllowedPaths
+                        ),
+                    )
+                    .at(&file.path, None),
+                );
+            }
+
+            if self.block_new_artifacts && file.added {
+                if let Some(kind) = artifact_kind(file) {
+                    findings.push(
+                        Finding::new(
+                            "new-artifact",
+                            format!(
+                                "New {} ({}) should not be committed",
+                                kind,
+                                human_size(file.size)
+                            ),
+                        )
+                        .at(&file.path, None),
+                    );
+                }
+            }
+        }
+
+        findings
+    }
+}
+
+/// `Some("archive")` or `Some("executable")` if `file` looks like a build artifact.
+fn artifact_kind(file: &ChangedFile) -> Option<&'static str> {
+    let name = file.path.rsplit('/').next().unwrap_or(&file.path);
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    if ARCHIVE_EXTENSIONS.contains(&extension.as_str()) {
+        Some("archive")
+    } else if EXECUTABLE_EXTENSIONS.contains(&extension.as_str())
+        || (file.is_executable() && file.binary)
+    {
+        Some("executable")
+    } else {
+        None
+    }
+}
+
+/// Format `bytes` using binary units, e.g. `1.5 MiB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
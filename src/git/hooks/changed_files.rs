@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::collections::HashMap;
+
+use crate::git::GitCommand;
+
+/// A file that is added or modified, either in the index or by a commit.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChangedFile {
+    pub path: String,
+    /// The new file mode, e.g. `100644`, `100755` or `120000` (symlink).
+    pub mode: String,
+    /// The object name of the new blob.
+    pub blob: String,
+    /// `true` if the file is newly added.
+    pub added: bool,
+    /// The size of the new blob in bytes.
+    pub size: u64,
+    /// `true` if Git considers the file binary (it contains NUL bytes, or `.gitattributes` marks it `binary`/`-diff`).
+    pub binary: bool,
+}
+
+impl ChangedFile {
+    /// List the files that are added or modified in the index relative to `base`; deleted files are skipped.
+    pub fn staged(base: &str) -> Result<Vec<ChangedFile>> {
+        Self::list("diff-index", &["--cached", base])
+    }
+
+    /// List the files that are added or modified by `commit`; deleted files are skipped.
+    ///
+    /// Nothing is listed for merge commits, since their changes come from the merged commits.
+    pub fn in_commit(commit: &str) -> Result<Vec<ChangedFile>> {
+        Self::list("diff-tree", &["-r", "--root", "--no-commit-id", commit])
+    }
+
+    /// `git SUBCOMMAND --raw ARGS` and `git SUBCOMMAND --numstat ARGS`, where `SUBCOMMAND` is `diff-index`/`diff-tree`.
+    fn list(subcommand: &str, args: &[&str]) -> Result<Vec<ChangedFile>> {
+        let raw_args: Vec<&str> = ["--raw", "-z", "--no-renames", "--no-abbrev"]
+            .iter()
+            .chain(args)
+            .copied()
+            .collect();
+
+        // `:OLD_MODE NEW_MODE OLD_SHA NEW_SHA STATUS\0PATH\0`
+        let raw = GitCommand::new(subcommand)
+            .with_default_args(&raw_args)
+            .read_stdout()?;
+
+        let mut files: Vec<ChangedFile> = Vec::new();
+        let mut fields = raw.split('\0');
+
+        while let (Some(info), Some(path)) = (fields.next(), fields.next()) {
+            let parts: Vec<&str> = info.trim_start_matches(':').split(' ').collect();
+            let [_, mode, _, blob, status] = parts[..] else {
+                continue;
+            };
+
+            if status == "D" || mode == "160000" {
+                // deleted files and submodules have no blob in the index
+                continue;
+            }
+
+            files.push(ChangedFile {
+                path: path.to_string(),
+                mode: mode.to_string(),
+                blob: blob.to_string(),
+                added: status == "A",
+                size: 0,
+                binary: false,
+            });
+        }
+
+        if files.is_empty() {
+            return Ok(files);
+        }
+
+        let sizes = blob_sizes(&files)?;
+        let binary = binary_paths(subcommand, args)?;
+
+        for file in &mut files {
+            file.size = sizes.get(&file.blob).copied().unwrap_or_default();
+            file.binary = binary.contains(&file.path);
+        }
+
+        debug!("changed files: {:#?}", files);
+
+        Ok(files)
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.mode == "100755"
+    }
+}
+
+/// `git cat-file --batch-check='%(objectname) %(objectsize)'`
+fn blob_sizes(files: &[ChangedFile]) -> Result<HashMap<String, u64>> {
+    let input: String = files.iter().map(|f| format!("{}\n", f.blob)).collect();
+
+    let output = GitCommand::new("cat-file")
+        .with_default_args(&["--batch-check=%(objectname) %(objectsize)"])
+        .capture_with_stdin(input.as_bytes())?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to get file sizes: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (blob, size) = line.split_once(' ')?;
+            Some((blob.to_string(), size.parse().ok()?))
+        })
+        .collect())
+}
+
+/// The changed paths Git treats as binary; `--numstat` reports `-` for their added/deleted line counts.
+fn binary_paths(subcommand: &str, args: &[&str]) -> Result<Vec<String>> {
+    let numstat_args: Vec<&str> = ["--numstat", "-z", "--no-renames"]
+        .iter()
+        .chain(args)
+        .copied()
+        .collect();
+
+    let numstat = GitCommand::new(subcommand)
+        .with_default_args(&numstat_args)
+        .read_stdout()?;
+
+    Ok(numstat
+        .split('\0')
+        .filter_map(|entry| entry.strip_prefix("-\t-\t"))
+        .map(str::to_string)
+        .collect())
+}
@@ -0,0 +1,63 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use regex::Regex;
+use std::env::{self, VarError};
+
+use super::Finding;
+use crate::git::{diff::FilePatch, env_vars::GitUtilEnvVars};
+
+/// The case-insensitive Regex built from the env value `$GIT_UTIL_DISALLOWED_STRINGS`, if it is set.
+pub fn disallowed_strings() -> Result<Option<Regex>> {
+    match env::var(String::from(GitUtilEnvVars::DisallowedStrings)) {
+        Ok(disallowed_strings) => {
+            debug!(
+                "{}=\"{}\"",
+                GitUtilEnvVars::DisallowedStrings,
+                disallowed_strings
+            );
+
+            Ok(Some(
+                Regex::new(&format!("(?i){}", disallowed_strings)).with_context(|| {
+                    format!("Invalid {} regex", GitUtilEnvVars::DisallowedStrings)
+                })?,
+            ))
+        }
+        Err(VarError::NotPresent) => {
+            debug!(
+                "{} not found; skipping check",
+                GitUtilEnvVars::DisallowedStrings
+            );
+            Ok(None)
+        }
+        // env var exists, but there's some other problem with it
+        Err(err) => Err(anyhow!(
+            "failed to get env variable {}: {}",
+            GitUtilEnvVars::DisallowedStrings,
+            err
+        )),
+    }
+}
+
+/// Find lines added in `patches` that match `disallowed`.
+pub fn check_disallowed_strings(patches: &[FilePatch], disallowed: &Regex) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+
+    for patch in patches {
+        for line in &patch.added_lines {
+            if let Some(found) = disallowed.find(&line.text) {
+                findings.push(
+                    Finding::new(
+                        "disallowed-string",
+                        format!("Disallowed addition: {}", line.text.trim()),
+                    )
+                    .at(&patch.path, Some(line.number))
+                    .at_column(line.text[..found.start()].chars().count() + 1),
+                );
+            }
+        }
+    }
+
+    debug!("{} disallowed addition(s) found", findings.len());
+
+    findings
+}
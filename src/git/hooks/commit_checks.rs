@@ -0,0 +1,196 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use regex::Regex;
+use std::io::{BufRead, BufReader};
+
+use super::{
+    blobs::BlobPolicy,
+    changed_files::ChangedFile,
+    checks,
+    email_policy::{EmailPolicy, Identity},
+    signing::SigningPolicy,
+    text::TextPolicy,
+    Finding,
+};
+use crate::git::{diff, journal::JOURNAL_REFS, GitCommand, GitResult};
+
+/// Separates the commits in the `git log` output; the fields of each commit header are separated by `\x1f`.
+const RECORD_SEPARATOR: char = '\x1e';
+
+/// A commit read from `git log`, along with its changes if they are needed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LoggedCommit {
+    pub hash: String,
+    pub author_email: String,
+    pub committer_email: String,
+    pub subject: String,
+    /// The `--patch` output for the commit; empty unless disallowed strings or the `TextPolicy` are being checked.
+    patch: String,
+}
+
+/// The author and content rules that are applied to existing commits by the `pre-push` hook and `hook check`.
+#[derive(Debug, Clone)]
+pub struct CommitChecks {
+    email_policy: Option<EmailPolicy>,
+    disallowed: Option<Regex>,
+    text_policy: TextPolicy,
+    blob_policy: BlobPolicy,
+    signing_policy: SigningPolicy,
+}
+
+impl LoggedCommit {
+    pub fn short_hash(&self) -> &str {
+        &self.hash[..self.hash.len().min(10)]
+    }
+
+    /// Parse a `git log` record: `HASH\x1fAUTHOR_EMAIL\x1fCOMMITTER_EMAIL\x1fSUBJECT\n[PATCH]`.
+    fn parse(record: &str) -> Option<LoggedCommit> {
+        let (header, patch) = record.split_once('\n').unwrap_or((record, ""));
+        let mut fields = header.split('\x1f');
+
+        Some(LoggedCommit {
+            hash: fields.next()?.to_string(),
+            author_email: fields.next()?.to_string(),
+            committer_email: fields.next()?.to_string(),
+            subject: fields.next().unwrap_or_default().to_string(),
+            patch: patch.to_string(),
+        })
+    }
+}
+
+impl CommitChecks {
+    /// Load the email policy, `$GIT_UTIL_DISALLOWED_STRINGS`, the text, blob and signing policies.
+    pub fn from_config() -> Result<CommitChecks> {
+        Ok(CommitChecks {
+            email_policy: EmailPolicy::for_repository()?,
+            disallowed: checks::disallowed_strings()?,
+            text_policy: TextPolicy::from_config()?,
+            blob_policy: BlobPolicy::from_config()?,
+            signing_policy: SigningPolicy::from_config()?,
+        })
+    }
+
+    /// Check every commit selected by the `git log` revision arguments in `revisions` (e.g. `A..B` or `--all`).
+    ///
+    /// `extra` is called with each commit to run additional checks; it is responsible for setting `Finding::commit`.
+    /// Returns the number of commits checked and the findings, which identify the offending commit (and file and line,
+    /// for content findings).
+    pub fn check_commits<F>(
+        &self,
+        revisions: &[String],
+        mut extra: F,
+    ) -> Result<(usize, Vec<Finding>)>
+    where
+        F: FnMut(&LoggedCommit) -> Vec<Finding>,
+    {
+        let mut default_args = vec![format!("--format={RECORD_SEPARATOR}%H%x1f%ae%x1f%ce%x1f%s")];
+        if self.needs_patch() {
+            default_args.push("--patch".to_string());
+            default_args.push("--find-renames".to_string());
+        }
+        let default_args: Vec<&str> = default_args.iter().map(String::as_str).collect();
+
+        let mut child = GitCommand::new("log")
+            .with_default_args(&default_args)
+            .with_user_args(revisions)
+            .spawn_with_piped_stdout()?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .with_context(|| "Failed to open stdout of 'git log'")?;
+
+        let mut count = 0;
+        let mut findings: Vec<Finding> = Vec::new();
+        let mut record = String::new();
+        let mut line: Vec<u8> = Vec::new();
+        let mut reader = BufReader::new(stdout);
+
+        // stream the log one commit at a time, since the patches for a whole history can be huge
+        loop {
+            line.clear();
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .with_context(|| "Failed to read 'git log' output")?;
+            let text = String::from_utf8_lossy(&line);
+
+            if read == 0 || text.starts_with(RECORD_SEPARATOR) {
+                if let Some(commit) = LoggedCommit::parse(&record) {
+                    count += 1;
+                    findings.extend(self.check(&commit)?);
+                    findings.extend(extra(&commit));
+                }
+
+                if read == 0 {
+                    break;
+                }
+
+                record.clear();
+                record.push_str(text.trim_start_matches(RECORD_SEPARATOR));
+            } else {
+                record.push_str(&text);
+            }
+        }
+
+        if !child.wait()?.success() {
+            return Err(anyhow!("'git log {}' failed", revisions.join(" ")));
+        }
+
+        Ok((count, findings))
+    }
+
+    fn needs_patch(&self) -> bool {
+        self.disallowed.is_some() || self.text_policy.is_enabled()
+    }
+
+    fn check(&self, commit: &LoggedCommit) -> Result<Vec<Finding>> {
+        debug!("checking commit {}", commit.hash);
+
+        let mut findings: Vec<Finding> = Vec::new();
+
+        if let Some(policy) = &self.email_policy {
+            findings.extend(policy.check(Identity::Author, &commit.author_email));
+            findings.extend(policy.check(Identity::Committer, &commit.committer_email));
+        }
+
+        findings.extend(self.signing_policy.check_commit(&commit.hash)?);
+
+        if self.needs_patch() {
+            let patches = diff::parse_patch(&commit.patch);
+
+            if let Some(disallowed) = &self.disallowed {
+                findings.extend(checks::check_disallowed_strings(&patches, disallowed));
+            }
+
+            findings.extend(self.text_policy.check(&patches));
+        }
+
+        if self.blob_policy.is_enabled() {
+            findings.extend(
+                self.blob_policy
+                    .check(&ChangedFile::in_commit(&commit.hash)?),
+            );
+        }
+
+        Ok(findings
+            .into_iter()
+            .map(|f| f.in_commit(commit.short_hash()))
+            .collect())
+    }
+}
+
+/// Run `hook check`: apply the author and content rules to every commit in `range` (e.g. `main..feature`), or to every
+/// commit reachable from any ref if `range` is `None`.
+pub fn check_history(range: Option<&str>) -> GitResult {
+    // the undo journal's snapshots aren't commits anyone made
+    let revisions: Vec<String> = match range {
+        Some(range) => vec![range.to_string()],
+        None => vec![format!("--exclude={JOURNAL_REFS}/*"), "--all".to_string()],
+    };
+
+    let (count, findings) =
+        CommitChecks::from_config()?.check_commits(&revisions, |_| Vec::new())?;
+    info!("checked {} commit(s) in {}", count, revisions.join(" "));
+
+    super::report("check", &findings)
+}
@@ -0,0 +1,242 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use regex::Regex;
+use std::{fs, path::Path};
+
+use super::{handoff::Handoff, pre_commit::PreCommitHook, Finding};
+use crate::{
+    git::{
+        config::{GitUtilConfig, DEFAULT_CONVENTIONAL_TYPES},
+        message::{self, CommitMessage, ConventionalSubject},
+        Git, GitResult,
+    },
+    glob::Glob,
+};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct CommitMsgHook {}
+
+/// The rules a commit message is checked against; see `GitUtilConfig` for the corresponding config keys.
+#[derive(Debug, Clone)]
+pub struct CommitMsgRules {
+    conventional: bool,
+    types: Vec<String>,
+    scopes: Vec<String>,
+    max_subject_length: Option<usize>,
+    require_blank_line: bool,
+    max_body_line_length: Option<usize>,
+    required_trailers: Vec<String>,
+    forbidden_words: Vec<String>,
+    protected_branches: Vec<Glob>,
+}
+
+impl CommitMsgHook {
+    /// Run the commit-msg hook against the message in `file` (the path Git passes to the hook).
+    ///
+    /// Comment lines (per `core.commentChar`) are removed before the message is checked against `CommitMsgRules`.
+    /// Fails if the message violates any of the configured rules, or if it is still just what the `prepare-commit-msg`
+    /// hook filled in for an empty message. When amending (as seen by `prepare-commit-msg`), the pre-commit checks on
+    /// the changes are also run against the parents of the amended commit.
+    pub fn run(file: &Path) -> GitResult {
+        info!("Running commit-msg hook on {}", file.display());
+
+        let raw = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read commit message file {}", file.display()))?;
+
+        let stripped = message::strip_comments(&raw, message::comment_char(&raw)?);
+        let message = CommitMessage::parse(&stripped);
+        debug!("parsed commit message: {:#?}", message);
+
+        // nothing was written in the editor, so Git would have aborted if the hook hadn't filled anything in
+        if Handoff::PreparedMessage.take()?.as_deref() == Some(stripped.as_str()) {
+            return super::report(
+                "commit-msg",
+                &[Finding::new(
+                    "commit-msg/empty-message",
+                    "Aborting commit due to empty commit message (it only has what the prepare-commit-msg hook filled in)"
+                        .to_string(),
+                )],
+            );
+        }
+
+        let rules = CommitMsgRules::from_config()?;
+        let mut findings = rules.check(
+            &message,
+            &file.display().to_string(),
+            Git::current_branch()?.as_deref(),
+        )?;
+
+        if Handoff::Amend.take()?.is_some() {
+            findings.extend(PreCommitHook::check_amend()?);
+        }
+
+        super::report("commit-msg", &findings)
+    }
+}
+
+impl CommitMsgRules {
+    /// Load the rules from the `git-util.commit-msg.*` config settings.
+    pub fn from_config() -> Result<CommitMsgRules> {
+        let types = GitUtilConfig::CommitMsgTypes.get_all()?;
+
+        Ok(CommitMsgRules {
+            conventional: GitUtilConfig::CommitMsgConventional
+                .get_bool()?
+                .unwrap_or(false),
+            types: if types.is_empty() {
+                DEFAULT_CONVENTIONAL_TYPES
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect()
+            } else {
+                types
+            },
+            scopes: GitUtilConfig::CommitMsgScopes.get_all()?,
+            max_subject_length: GitUtilConfig::CommitMsgMaxSubjectLength.get_usize()?,
+            require_blank_line: GitUtilConfig::CommitMsgRequireBlankLine
+                .get_bool()?
+                .unwrap_or(true),
+            max_body_line_length: GitUtilConfig::CommitMsgMaxBodyLineLength.get_usize()?,
+            required_trailers: GitUtilConfig::CommitMsgRequiredTrailers.get_all()?,
+            forbidden_words: GitUtilConfig::CommitMsgForbiddenWords.get_all()?,
+            protected_branches: Glob::new_all(&GitUtilConfig::ProtectedBranches.get_all()?)?,
+        })
+    }
+
+    /// Check `message` against the rules.
+    ///
+    /// `source` identifies where the message came from in findings; `branch` is the branch being committed to, if any.
+    pub fn check(
+        &self,
+        message: &CommitMessage,
+        source: &str,
+        branch: Option<&str>,
+    ) -> Result<Vec<Finding>> {
+        let mut findings: Vec<Finding> = Vec::new();
+
+        if message.lines.is_empty() {
+            // git aborts the commit on an empty message
+            return Ok(findings);
+        }
+
+        if self.conventional && !message.is_generated() {
+            findings.extend(self.check_conventional(&message.subject, source));
+        }
+
+        if let Some(max) = self.max_subject_length {
+            let length = message.subject.chars().count();
+            if length > max {
+                findings.push(
+                    Finding::new(
+                        "commit-msg/subject-length",
+                        format!("Subject is {length} characters long; the maximum is {max}"),
+                    )
+                    .at(source, Some(1)),
+                );
+            }
+        }
+
+        if self.require_blank_line && message.lines.len() > 1 && !message.lines[1].is_empty() {
+            findings.push(
+                Finding::new(
+                    "commit-msg/blank-line",
+                    "The line after the subject must be blank".to_string(),
+                )
+                .at(source, Some(2)),
+            );
+        }
+
+        if let Some(max) = self.max_body_line_length {
+            for (i, line) in message.lines.iter().enumerate().skip(1) {
+                let length = line.chars().count();
+                // a line without spaces (e.g. a URL) can't be wrapped
+                if length > max && line.trim().contains(' ') {
+                    findings.push(
+                        Finding::new(
+                            "commit-msg/body-line-length",
+                            format!("Body line is {length} characters long; the maximum is {max}"),
+                        )
+                        .at(source, Some(i + 1)),
+                    );
+                }
+            }
+        }
+
+        for token in &self.required_trailers {
+            if message.trailer_values(token).is_empty() {
+                findings.push(
+                    Finding::new(
+                        "commit-msg/required-trailer",
+                        format!("Missing required trailer \"{token}: <value>\""),
+                    )
+                    .at(source, None),
+                );
+            }
+        }
+
+        if !self.forbidden_words.is_empty() && self.is_protected(branch) {
+            for word in &self.forbidden_words {
+                let re = Regex::new(&format!(r"(?i)\b{}\b", regex::escape(word)))
+                    .map_err(|e| anyhow!("Invalid forbidden word \"{word}\": {e}"))?;
+
+                if let Some(i) = message.lines.iter().position(|l| re.is_match(l)) {
+                    findings.push(
+                        Finding::new(
+                            "commit-msg/forbidden-word",
+                            format!(
+                                "\"{}\" is not allowed in commit messages on branch {}",
+                                word,
+                                branch.unwrap_or("HEAD")
+                            ),
+                        )
+                        .at(source, Some(i + 1)),
+                    );
+                }
+            }
+        }
+
+        Ok(findings)
+    }
+
+    /// Forbidden words apply on every branch unless protected branches are configured.
+    fn is_protected(&self, branch: Option<&str>) -> bool {
+        if self.protected_branches.is_empty() {
+            return true;
+        }
+
+        match branch {
+            Some(branch) => self.protected_branches.iter().any(|g| g.is_match(branch)),
+            None => false,
+        }
+    }
+
+    fn check_conventional(&self, subject: &str, source: &str) -> Option<Finding> {
+        let finding =
+            |message: String| Finding::new("commit-msg/conventional", message).at(source, Some(1));
+
+        let Some(parsed) = ConventionalSubject::parse(subject) else {
+            return Some(finding(
+                "Subject must be in the form \"type(scope)!: description\"".to_string(),
+            ));
+        };
+
+        if !self.types.contains(&parsed.kind) {
+            return Some(finding(format!(
+                "Unknown type \"{}\"; expected one of: {}",
+                parsed.kind,
+                self.types.join(", ")
+            )));
+        }
+
+        match parsed.scope {
+            Some(scope) if !self.scopes.is_empty() && !self.scopes.contains(&scope) => {
+                Some(finding(format!(
+                    "Unknown scope \"{}\"; expected one of: {}",
+                    scope,
+                    self.scopes.join(", ")
+                )))
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::{anyhow, Context, Result};
+use core::fmt;
+use log::debug;
+use regex::Regex;
+use std::env::{self, VarError};
+
+use super::Finding;
+use crate::{
+    git::{
+        config::{self, expand_home, GitUtilConfig},
+        env_vars::{GitEnvVars, GitUtilEnvVars},
+        GitCommand,
+    },
+    glob::Glob,
+};
+
+/// The identity on a commit that an email address belongs to.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Identity {
+    Author,
+    Committer,
+}
+
+/// A set of allowed emails, applied to repositories whose remote URLs or paths match.
+///
+/// Configured as `git-util.email-rule.<name>.<setting>`, where each setting can be repeated:
+///
+/// - `remote`: glob matched against the URL of each remote, e.g. `*github.com:mycorp/*`
+/// - `path`: glob matched against the root directory of the work tree, e.g. `~/work/*`
+/// - `email`: an allowed email address
+/// - `email-pattern`: a Regex that allowed email addresses match in full
+///
+/// A rule without any `remote` or `path` globs applies to every repository.
+#[derive(Debug, Clone)]
+struct EmailRule {
+    name: String,
+    remotes: Vec<Glob>,
+    paths: Vec<Glob>,
+    emails: Vec<String>,
+    /// `(pattern, anchored Regex)` pairs.
+    patterns: Vec<(String, Regex)>,
+}
+
+/// The email rule that applies to the current repository, and why it applies.
+#[derive(Debug, Clone)]
+pub struct EmailPolicy {
+    rule: EmailRule,
+    reason: String,
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identity::Author => write!(f, "author"),
+            Identity::Committer => write!(f, "committer"),
+        }
+    }
+}
+
+impl Identity {
+    /// The email of the identity Git will use for the next commit.
+    ///
+    /// `$GIT_AUTHOR_EMAIL` is used if set (Git sets it for hooks); otherwise the email is read from
+    /// `git var GIT_AUTHOR_IDENT`/`GIT_COMMITTER_IDENT`.
+    pub fn current_email(self) -> Result<String> {
+        if self == Identity::Author {
+            if let Ok(email) = env::var(String::from(GitEnvVars::AuthorEmail)) {
+                return Ok(email);
+            }
+        }
+
+        let var = match self {
+            Identity::Author => "GIT_AUTHOR_IDENT",
+            Identity::Committer => "GIT_COMMITTER_IDENT",
+        };
+
+        let ident = GitCommand::new("var")
+            .with_default_args(&[var])
+            .read_stdout()?;
+
+        // `NAME <EMAIL> TIMESTAMP TIMEZONE`
+        ident
+            .split_once('<')
+            .and_then(|(_, rest)| rest.split_once('>'))
+            .map(|(email, _)| email.to_string())
+            .ok_or_else(|| anyhow!("Failed to parse email from {var} \"{}\"", ident.trim()))
+    }
+
+    fn rule_id(self) -> &'static str {
+        match self {
+            Identity::Author => "author-email",
+            Identity::Committer => "committer-email",
+        }
+    }
+}
+
+impl EmailRule {
+    fn is_allowed(&self, email: &str) -> bool {
+        self.emails.iter().any(|e| e.eq_ignore_ascii_case(email))
+            || self.patterns.iter().any(|(_, re)| re.is_match(email))
+    }
+
+    /// A description of the allowed emails for error messages.
+    fn expected(&self) -> String {
+        let mut expected: Vec<String> = self.emails.iter().map(|e| format!("\"{e}\"")).collect();
+        expected.extend(self.patterns.iter().map(|(p, _)| format!("/{p}/")));
+        expected.join(" or ")
+    }
+}
+
+impl EmailPolicy {
+    /// Find the policy for the current repository.
+    ///
+    /// The first `git-util.email-rule` (in config order) whose `remote` or `path` globs match is used. If none match,
+    /// the env value `$GIT_UTIL_USER_EMAIL` is used, if set. Returns `None` if there is nothing to check against.
+    pub fn for_repository() -> Result<Option<EmailPolicy>> {
+        let rules = Self::load_rules()?;
+
+        if !rules.is_empty() {
+            let remote_urls: Vec<(String, String)> = config::get_regexp(r"^remote\..*\.url$")?
+                .into_iter()
+                .map(|(key, url)| {
+                    let remote = key
+                        .trim_start_matches("remote.")
+                        .trim_end_matches(".url")
+                        .to_string();
+                    (remote, url)
+                })
+                .collect();
+
+            let top_level = GitCommand::new("rev-parse")
+                .with_default_args(&["--show-toplevel"])
+                .read_stdout()
+                .map(|path| path.trim().to_string())
+                .unwrap_or_default();
+
+            for rule in rules {
+                if let Some(reason) = Self::match_reason(&rule, &remote_urls, &top_level) {
+                    debug!("email rule \"{}\" applies: {}", rule.name, reason);
+                    return Ok(Some(EmailPolicy { rule, reason }));
+                }
+            }
+        }
+
+        match env::var(String::from(GitUtilEnvVars::UserEmail)) {
+            Ok(email) => Ok(Some(EmailPolicy {
+                rule: EmailRule {
+                    name: GitUtilEnvVars::UserEmail.to_string(),
+                    remotes: Vec::new(),
+                    paths: Vec::new(),
+                    emails: vec![email],
+                    patterns: Vec::new(),
+                },
+                reason: format!("{} is set", GitUtilEnvVars::UserEmail),
+            })),
+            Err(VarError::NotPresent) => {
+                debug!(
+                    "no email rule applies and {} is not set; skipping email checks",
+                    GitUtilEnvVars::UserEmail
+                );
+                Ok(None)
+            }
+            Err(err) => Err(anyhow!(
+                "failed to get env variable {}: {}",
+                GitUtilEnvVars::UserEmail,
+                err
+            )),
+        }
+    }
+
+    /// Check the `identity` email.
+    pub fn check(&self, identity: Identity, email: &str) -> Option<Finding> {
+        if self.rule.is_allowed(email) {
+            return None;
+        }
+
+        Some(Finding::new(
+            identity.rule_id(),
+            format!(
+                "Invalid {} email \"{}\". Expected: {} (rule \"{}\" applies because {})",
+                identity,
+                email,
+                self.rule.expected(),
+                self.rule.name,
+                self.reason
+            ),
+        ))
+    }
+
+    /// Parse the `git-util.email-rule.<name>.<setting>` settings into rules, in config order.
+    fn load_rules() -> Result<Vec<EmailRule>> {
+        let mut rules: Vec<EmailRule> = Vec::new();
+
+        for subsection in config::get_subsections(GitUtilConfig::EmailRules)? {
+            let mut rule = EmailRule {
+                name: subsection.name,
+                remotes: Vec::new(),
+                paths: Vec::new(),
+                emails: Vec::new(),
+                patterns: Vec::new(),
+            };
+
+            for (key, setting, value) in subsection.settings {
+                match setting.as_str() {
+                    "remote" => rule.remotes.push(Glob::new(&value)?),
+                    "path" => rule
+                        .paths
+                        .push(Glob::new(&expand_home(&value).to_string_lossy())?),
+                    "email" => rule.emails.push(value),
+                    "email-pattern" => {
+                        let re = Regex::new(&format!("^(?:{value})$"))
+                            .with_context(|| format!("Invalid {key} \"{value}\""))?;
+                        rule.patterns.push((value, re));
+                    }
+                    _ => return Err(anyhow!("Unknown email rule setting {key}")),
+                }
+            }
+
+            rules.push(rule);
+        }
+
+        if let Some(rule) = rules
+            .iter()
+            .find(|r| r.emails.is_empty() && r.patterns.is_empty())
+        {
+            return Err(anyhow!(
+                "Email rule \"{}\" has no {}.{}.email or {}.{}.email-pattern",
+                rule.name,
+                GitUtilConfig::EmailRules,
+                rule.name,
+                GitUtilConfig::EmailRules,
+                rule.name
+            ));
+        }
+
+        Ok(rules)
+    }
+
+    fn match_reason(
+        rule: &EmailRule,
+        remote_urls: &[(String, String)],
+        top_level: &str,
+    ) -> Option<String> {
+        if rule.remotes.is_empty() && rule.paths.is_empty() {
+            return Some("it applies to every repository".to_string());
+        }
+
+        for glob in &rule.remotes {
+            if let Some((remote, url)) = remote_urls.iter().find(|(_, url)| glob.is_match(url)) {
+                return Some(format!(
+                    "remote \"{}\" URL {} matches \"{}\"",
+                    remote,
+                    url,
+                    glob.as_str()
+                ));
+            }
+        }
+
+        rule.paths
+            .iter()
+            .find(|glob| glob.is_match(top_level))
+            .map(|glob| format!("path {} matches \"{}\"", top_level, glob.as_str()))
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use crate::git::GitCommand;
+
+/// What the `prepare-commit-msg` hook leaves for the `commit-msg` hook of the same commit, in `.git/git-util/`.
+///
+/// `prepare-commit-msg` clears every handoff before leaving any, so one left by a commit that was aborted (or made with
+/// `--no-verify`) is never picked up by the next.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Handoff {
+    /// The message `prepare-commit-msg` filled in when Git gave it an empty one, without comments.
+    PreparedMessage,
+    /// The commit is amending `HEAD`, so `commit-msg` checks the changes relative to its parents.
+    Amend,
+}
+
+impl Handoff {
+    const ALL: [Handoff; 2] = [Handoff::PreparedMessage, Handoff::Amend];
+
+    /// Remove every handoff.
+    pub fn clear_all() -> Result<()> {
+        for handoff in Handoff::ALL {
+            handoff.take()?;
+        }
+        Ok(())
+    }
+
+    pub fn write(self, contents: &str) -> Result<()> {
+        let path = self.path()?;
+        debug!("writing {:?} handoff to {}", self, path.display());
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+
+        fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Read and remove the handoff, or `None` if there isn't one.
+    pub fn take(self) -> Result<Option<String>> {
+        let path = self.path()?;
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+
+        Ok(Some(contents))
+    }
+
+    /// `.git/git-util/<name>`, or its equivalent for a linked worktree.
+    fn path(self) -> Result<PathBuf> {
+        let name = match self {
+            Handoff::PreparedMessage => "git-util/prepared-message",
+            Handoff::Amend => "git-util/amend",
+        };
+
+        let path = GitCommand::new("rev-parse")
+            .with_default_args(&["--git-path", name])
+            .read_stdout()?;
+
+        Ok(PathBuf::from(path.trim()))
+    }
+}
@@ -0,0 +1,463 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, trace};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+
+use super::Hook;
+use crate::{
+    git::{
+        config::{self, expand_home, home_dir, GitUtilConfig},
+        GitCommand, GitCommandResult, GitResult, DRY_RUN,
+    },
+    print::Print,
+};
+
+/// Identifies a hook script as a shim written by `git-util hook install`.
+const SHIM_MARKER: &str = "git-util-hook-shim";
+
+/// A hook that existed before the shim was installed is renamed to `<hook><CHAINED_SUFFIX>` and run by the shim.
+const CHAINED_SUFFIX: &str = ".git-util-chained";
+
+/// The other hooks Git runs by name, which the global shims pass through to the repository's hook and the previous global
+/// `core.hooksPath`, since setting `core.hooksPath` would otherwise stop Git from running them (e.g. Git LFS's
+/// `post-checkout`).
+///
+/// `push-to-checkout` and `proc-receive` are left out, since Git changes what `git push` does when they merely exist.
+const PASS_THROUGH_HOOKS: [&str; 21] = [
+    "applypatch-msg",
+    "pre-applypatch",
+    "post-applypatch",
+    "pre-merge-commit",
+    "post-commit",
+    "pre-rebase",
+    "post-checkout",
+    "post-merge",
+    "pre-receive",
+    "update",
+    "post-receive",
+    "post-update",
+    "reference-transaction",
+    "pre-auto-gc",
+    "post-rewrite",
+    "sendemail-validate",
+    "post-index-change",
+    "p4-changelist",
+    "p4-prepare-changelist",
+    "p4-post-changelist",
+    "p4-pre-submit",
+];
+
+/// The hooks Git passes input to on stdin, which is saved so it can be replayed to each command a shim runs.
+const STDIN_HOOKS: [&str; 5] = [
+    "pre-push",
+    "pre-receive",
+    "post-receive",
+    "reference-transaction",
+    "post-rewrite",
+];
+
+/// Install shims for `hooks` that call the running **git-util** executable.
+///
+/// - Local: the shims are written to the repository's hooks directory (`core.hooksPath` from the local config, else
+///   `.git/hooks`). Existing hooks are renamed to `<hook>.git-util-chained` and run by the shim first. Fails if a
+///   `core.hooksPath` that **git-util** doesn't manage is set elsewhere, since Git wouldn't run the shims.
+/// - Global: the shims are written to a directory managed by **git-util**, which is set as the global
+///   `core.hooksPath`. A previously set global `core.hooksPath` is remembered, and its hooks are chained. Since Git
+///   ignores `.git/hooks` when `core.hooksPath` is set, the global shims also run each repository's own hooks, and
+///   shims that only do that (and run the previous global hook) are written for the `PASS_THROUGH_HOOKS`.
+pub fn install(hooks: &[Hook], global: bool) -> GitResult {
+    trace!("install() called with: {:#?}, global={}", hooks, global);
+
+    let exe = env::current_exe().with_context(|| "Failed to get path of git-util executable")?;
+
+    if global {
+        let dir = managed_global_hooks_dir()?;
+        let current = config::get_scoped("core.hooksPath", "--global")?;
+
+        if current.as_deref().map(expand_home) != Some(dir.clone()) {
+            if let Some(previous) = &current {
+                config::set_scoped(
+                    &GitUtilConfig::HooksPreviousGlobalPath.to_string(),
+                    previous,
+                    "--global",
+                )?;
+            }
+            config::set_scoped("core.hooksPath", &dir.to_string_lossy(), "--global")?;
+        }
+
+        let previous = config::get_scoped(
+            &GitUtilConfig::HooksPreviousGlobalPath.to_string(),
+            "--global",
+        )?
+        .map(|p| expand_home(&p));
+
+        create_dir(&dir)?;
+
+        let chained = |name: &str| -> Vec<PathBuf> {
+            previous
+                .iter()
+                .map(|p| p.join(name))
+                .filter(|p| p.is_file())
+                .collect()
+        };
+
+        for hook in hooks {
+            let name = hook.to_string();
+            write_file(
+                &dir.join(&name),
+                &shim(&name, Some(&exe), &chained(&name), true),
+            )?;
+        }
+
+        for name in PASS_THROUGH_HOOKS {
+            write_file(&dir.join(name), &shim(name, None, &chained(name), true))?;
+        }
+
+        Print::stderr_purple(&format!(
+            "Installed global hook(s) in {}: {} (other hooks are passed through to the chained hooks)",
+            dir.display(),
+            join(hooks)
+        ));
+    } else {
+        let dir = local_hooks_dir()?;
+        check_hooks_path_is_local(&dir)?;
+        create_dir(&dir)?;
+
+        for hook in hooks {
+            let path = dir.join(hook.to_string());
+            let chained_path = dir.join(format!("{hook}{CHAINED_SUFFIX}"));
+
+            let mut chained: Vec<PathBuf> = Vec::new();
+
+            if path.is_file() && !is_shim(&path)? {
+                if chained_path.exists() {
+                    return Err(anyhow!(
+                        "Can't preserve {}; {} already exists",
+                        path.display(),
+                        chained_path.display()
+                    ));
+                }
+                info!("preserving existing hook {}", path.display());
+                rename(&path, &chained_path)?;
+                chained.push(chained_path);
+            } else if chained_path.is_file() {
+                // preserved by a previous install
+                chained.push(chained_path);
+            }
+
+            write_file(&path, &shim(&hook.to_string(), Some(&exe), &chained, false))?;
+        }
+
+        Print::stderr_purple(&format!(
+            "Installed hook(s) in {}: {}",
+            dir.display(),
+            join(hooks)
+        ));
+    }
+
+    Ok(GitCommandResult::Success)
+}
+
+/// Remove the shims for `hooks` installed by `install`, restoring any hooks they chained.
+///
+/// When every global shim has been removed, the pass-through shims are removed too, and the global `core.hooksPath` is
+/// restored to its previous value.
+pub fn uninstall(hooks: &[Hook], global: bool) -> GitResult {
+    trace!("uninstall() called with: {:#?}, global={}", hooks, global);
+
+    let dir = if global {
+        managed_global_hooks_dir()?
+    } else {
+        local_hooks_dir()?
+    };
+
+    for hook in hooks {
+        let path = dir.join(hook.to_string());
+        let chained_path = dir.join(format!("{hook}{CHAINED_SUFFIX}"));
+
+        if !path.is_file() {
+            debug!("{} is not installed", path.display());
+        } else if is_shim(&path)? {
+            remove_file(&path)?;
+
+            if chained_path.is_file() {
+                rename(&chained_path, &path)?;
+            }
+        } else {
+            Print::stderr_purple(&format!(
+                "{} was not installed by git-util; skipping",
+                path.display()
+            ));
+        }
+    }
+
+    if global && Hook::ALL.iter().all(|h| !dir.join(h.to_string()).is_file()) {
+        for name in PASS_THROUGH_HOOKS {
+            let path = dir.join(name);
+            if path.is_file() && is_shim(&path)? {
+                remove_file(&path)?;
+            }
+        }
+
+        let previous_key = GitUtilConfig::HooksPreviousGlobalPath.to_string();
+
+        match config::get_scoped(&previous_key, "--global")? {
+            Some(previous) => {
+                config::set_scoped("core.hooksPath", &previous, "--global")?;
+                config::unset_scoped(&previous_key, "--global")?;
+            }
+            None => {
+                if config::get_scoped("core.hooksPath", "--global")?
+                    .is_some_and(|p| expand_home(&p) == dir)
+                {
+                    config::unset_scoped("core.hooksPath", "--global")?;
+                }
+            }
+        }
+    }
+
+    Ok(GitCommandResult::Success)
+}
+
+/// Print which hooks are installed locally and globally, and which existing hooks they chain.
+pub fn status() -> GitResult {
+    trace!("status() called");
+
+    let global_dir = managed_global_hooks_dir()?;
+    let hooks_path = config::get_all("core.hooksPath", None)?
+        .pop()
+        .map(|p| expand_home(&p));
+
+    match GitCommand::new("rev-parse")
+        .with_default_args(&["--git-common-dir"])
+        .capture()?
+        .status
+        .success()
+    {
+        true => print_hooks_dir("Local", &local_hooks_dir()?)?,
+        false => println!("Local: not in a Git repository"),
+    }
+    print_hooks_dir("Global", &global_dir)?;
+
+    match hooks_path {
+        Some(path) if path == global_dir => {
+            println!("core.hooksPath is the global git-util hooks directory; local hooks are run by the global shims")
+        }
+        Some(path) => println!("core.hooksPath: {}", path.display()),
+        None => println!("core.hooksPath is not set"),
+    }
+
+    Ok(GitCommandResult::Success)
+}
+
+fn print_hooks_dir(label: &str, dir: &Path) -> Result<()> {
+    println!("{} hooks ({}):", label, dir.display());
+
+    for hook in Hook::ALL {
+        let path = dir.join(hook.to_string());
+        let chained_path = dir.join(format!("{hook}{CHAINED_SUFFIX}"));
+
+        let state = if !path.is_file() {
+            "not installed".to_string()
+        } else if is_shim(&path)? {
+            let mut state = format!("git-util ({})", shim_executable(&path)?);
+            if chained_path.is_file() {
+                state.push_str(&format!(", chains {}", chained_path.display()));
+            }
+            state
+        } else {
+            "other hook".to_string()
+        };
+
+        println!("  {:<20}{}", hook.to_string(), state);
+    }
+
+    Ok(())
+}
+
+/// The hooks directory for the current repository: the local `core.hooksPath` if set, else `$GIT_COMMON_DIR/hooks`.
+fn local_hooks_dir() -> Result<PathBuf> {
+    if let Some(hooks_path) = config::get_scoped("core.hooksPath", "--local")? {
+        // relative paths are relative to the root of the work tree
+        let top_level = GitCommand::new("rev-parse")
+            .with_default_args(&["--show-toplevel"])
+            .read_stdout()?;
+
+        return Ok(Path::new(top_level.trim()).join(expand_home(&hooks_path)));
+    }
+
+    let common_dir = GitCommand::new("rev-parse")
+        .with_default_args(&["--git-common-dir"])
+        .read_stdout()?;
+
+    Ok(env::current_dir()?.join(common_dir.trim()).join("hooks"))
+}
+
+/// Fail if Git won't run the hooks in the local hooks `dir`, because `core.hooksPath` is set outside the repository's
+/// config (e.g. globally) to a directory other than the one managed by **git-util**, whose shims run the local hooks.
+fn check_hooks_path_is_local(dir: &Path) -> Result<()> {
+    if config::get_scoped("core.hooksPath", "--local")?.is_some() {
+        return Ok(());
+    }
+
+    match config::get_all("core.hooksPath", None)?
+        .pop()
+        .map(|p| expand_home(&p))
+    {
+        Some(hooks_path) if hooks_path != managed_global_hooks_dir()? => Err(anyhow!(
+            "core.hooksPath is set to {}, so Git won't run hooks installed in {}; install them with --global, or unset \
+             core.hooksPath",
+            hooks_path.display(),
+            dir.display()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// `$XDG_CONFIG_HOME/git-util/hooks`, falling back to `$HOME/.config/git-util/hooks`.
+fn managed_global_hooks_dir() -> Result<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home_dir()?.join(".config"),
+    };
+
+    Ok(config_home.join("git-util").join("hooks"))
+}
+
+fn is_shim(path: &Path) -> Result<bool> {
+    Ok(fs::read_to_string(path)
+        .map(|contents| contents.contains(SHIM_MARKER))
+        .unwrap_or(false))
+}
+
+/// The **git-util** executable a shim calls.
+fn shim_executable(path: &Path) -> Result<String> {
+    let contents = fs::read_to_string(path)?;
+
+    Ok(contents
+        .lines()
+        .find_map(|line| line.strip_prefix("GIT_UTIL="))
+        .map(|exe| exe.trim_matches('\'').to_string())
+        .unwrap_or_default())
+}
+
+/// A `sh` script for the hook named `hook` that runs each of the `chained` hooks and then, unless it only passes the hook
+/// through (`exe` is `None`), `git-util hook <hook>` with the hook's arguments.
+///
+/// For the `STDIN_HOOKS`, the input is saved and replayed to each command. Global shims also run the repository's own
+/// hook (if it isn't a **git-util** shim), since Git won't.
+fn shim(hook: &str, exe: Option<&Path>, chained: &[PathBuf], global: bool) -> String {
+    let reads_stdin = STDIN_HOOKS.contains(&hook);
+    let run = |command: &str| {
+        if reads_stdin {
+            format!("printf '%s\\n' \"$input\" | {command} \"$@\"")
+        } else {
+            format!("{command} \"$@\"")
+        }
+    };
+
+    let mut script = format!(
+        "#!/bin/sh\n# {SHIM_MARKER}: installed by `git-util hook install`; remove with `git-util hook uninstall`\nset -e\n\n"
+    );
+
+    if let Some(exe) = exe {
+        script.push_str(&format!(
+            "GIT_UTIL={}\n",
+            sh_quote(&exe.to_string_lossy().replace('\\', "/"))
+        ));
+    }
+
+    if reads_stdin {
+        script.push_str("input=$(cat)\n");
+    }
+
+    for path in chained {
+        let path = sh_quote(&path.to_string_lossy().replace('\\', "/"));
+        script.push_str(&format!(
+            "\nif [ -x {path} ]; then\n    {}\nfi\n",
+            run(&path)
+        ));
+    }
+
+    if global {
+        script.push_str(&format!(
+            "\nrepo_hook=\"$(git rev-parse --git-common-dir)/hooks/{hook}\"\nif [ -x \"$repo_hook\" ] && ! grep -q {SHIM_MARKER} \"$repo_hook\"; then\n    {}\nfi\n",
+            run("\"$repo_hook\"")
+        ));
+    }
+
+    if exe.is_some() {
+        script.push('\n');
+        script.push_str(&run(&format!("\"$GIT_UTIL\" hook {hook}")));
+        script.push('\n');
+    }
+
+    script
+}
+
+/// Quote `value` for `sh`.
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn join(hooks: &[Hook]) -> String {
+    hooks
+        .iter()
+        .map(|h| h.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn create_dir(dir: &Path) -> Result<()> {
+    if DRY_RUN.load(Ordering::SeqCst) {
+        Print::stderr_purple(&format!("would create directory {}", dir.display()));
+        return Ok(());
+    }
+
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))
+}
+
+/// Write an executable file.
+fn write_file(path: &Path, contents: &str) -> Result<()> {
+    if DRY_RUN.load(Ordering::SeqCst) {
+        Print::stderr_purple(&format!("would write {}:\n{}", path.display(), contents));
+        return Ok(());
+    }
+
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Failed to make {} executable", path.display()))?;
+    }
+
+    Ok(())
+}
+
+fn rename(from: &Path, to: &Path) -> Result<()> {
+    if DRY_RUN.load(Ordering::SeqCst) {
+        Print::stderr_purple(&format!(
+            "would rename {} to {}",
+            from.display(),
+            to.display()
+        ));
+        return Ok(());
+    }
+
+    fs::rename(from, to)
+        .with_context(|| format!("Failed to rename {} to {}", from.display(), to.display()))
+}
+
+fn remove_file(path: &Path) -> Result<()> {
+    if DRY_RUN.load(Ordering::SeqCst) {
+        Print::stderr_purple(&format!("would remove {}", path.display()));
+        return Ok(());
+    }
+
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))
+}
@@ -0,0 +1,272 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{fs, path::Path, sync::atomic::Ordering};
+use tempfile::TempDir;
+
+use super::{changed_files::ChangedFile, Finding};
+use crate::{
+    commands::Commands,
+    git::{
+        config::{self, GitUtilConfig},
+        GitCommand, DRY_RUN,
+    },
+    glob::Glob,
+    print::Print,
+};
+
+/// A command that is run against the staged versions of the files matching its globs.
+///
+/// Configured as `git-util.linter.<name>.<setting>`:
+///
+/// - `command`: a shell command; the matching paths are appended as arguments, e.g. `rustfmt --check --edition 2021`
+/// - `pattern`: glob matched against the staged paths, e.g. `*.rs` (repeatable)
+/// - `fix`: the command rewrites the files it is given; any changes are re-staged
+#[derive(Debug, Clone)]
+struct Linter {
+    name: String,
+    command: String,
+    patterns: Vec<Glob>,
+    fix: bool,
+}
+
+/// The configured linters, in config order.
+#[derive(Debug, Clone)]
+pub struct LinterPolicy {
+    linters: Vec<Linter>,
+}
+
+/// A temporary directory holding the staged contents of the index; removed when dropped.
+struct StagedTree {
+    dir: TempDir,
+}
+
+impl LinterPolicy {
+    /// Parse the `git-util.linter.<name>.<setting>` settings.
+    pub fn from_config() -> Result<LinterPolicy> {
+        let mut linters: Vec<Linter> = Vec::new();
+
+        for subsection in config::get_subsections(GitUtilConfig::Linters)? {
+            let mut linter = Linter {
+                name: subsection.name,
+                command: String::new(),
+                patterns: Vec::new(),
+                fix: false,
+            };
+
+            for (key, setting, value) in subsection.settings {
+                match setting.as_str() {
+                    "command" => linter.command = value,
+                    "pattern" => linter.patterns.push(Glob::new(&value)?),
+                    // re-read so that Git normalizes `yes`, `on`, `1`, etc.
+                    "fix" => {
+                        linter.fix = config::get_all(&key, Some("bool"))?
+                            .pop()
+                            .is_some_and(|v| v == "true")
+                    }
+                    _ => return Err(anyhow!("Unknown linter setting {key}")),
+                }
+            }
+
+            linters.push(linter);
+        }
+
+        if let Some(linter) = linters
+            .iter()
+            .find(|l| l.command.is_empty() || l.patterns.is_empty())
+        {
+            return Err(anyhow!(
+                "Linter \"{}\" needs both {}.{}.command and {}.{}.pattern",
+                linter.name,
+                GitUtilConfig::Linters,
+                linter.name,
+                GitUtilConfig::Linters,
+                linter.name
+            ));
+        }
+
+        Ok(LinterPolicy { linters })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.linters.is_empty()
+    }
+
+    /// Run each linter against the staged versions of the matching `files`.
+    ///
+    /// The whole index is written to a temporary directory, which is where the commands are run, so unstaged changes in
+    /// the working tree are never checked, while the linters' own config files (`rustfmt.toml`, `.eslintrc`, etc.) are
+    /// still found. If a `fix` linter changes a file, the new contents are staged, and
+    /// are also written to the working tree if it had no unstaged changes to that file.
+    pub fn run(&self, files: &[ChangedFile]) -> Result<Vec<Finding>> {
+        let mut findings: Vec<Finding> = Vec::new();
+
+        // symlinks are staged as their target path, which isn't worth linting
+        let files: Vec<&ChangedFile> = files.iter().filter(|f| f.mode != "120000").collect();
+
+        // checked out once the first linter matches, and shared, so each sees the fixes of the ones before it
+        let mut tree: Option<StagedTree> = None;
+
+        for linter in &self.linters {
+            let matched: Vec<&ChangedFile> = files
+                .iter()
+                .copied()
+                .filter(|f| !f.binary && linter.patterns.iter().any(|g| g.is_path_match(&f.path)))
+                .collect();
+
+            if matched.is_empty() {
+                debug!("no staged files match linter \"{}\"", linter.name);
+                continue;
+            }
+
+            let paths: Vec<&str> = matched.iter().map(|f| f.path.as_str()).collect();
+
+            if DRY_RUN.load(Ordering::SeqCst) {
+                Print::stderr_purple(&format!(
+                    "linter \"{}\" would run: `{} {}`",
+                    linter.name,
+                    linter.command,
+                    paths.join(" ")
+                ));
+                continue;
+            }
+
+            let tree = match &mut tree {
+                Some(tree) => tree,
+                None => tree.insert(StagedTree::checkout()?),
+            };
+
+            let status = Commands::new_command_with_args(
+                "sh",
+                &["-c", &format!("{} \"$@\"", linter.command), &linter.name],
+            )
+            .args(&paths)
+            .current_dir(tree.dir.path())
+            .status()
+            .with_context(|| format!("Failed to run linter \"{}\"", linter.name))?;
+
+            if !status.success() {
+                let mut finding = Finding::new(
+                    "linter",
+                    format!(
+                        "Linter \"{}\" failed ({}) for: {}",
+                        linter.name,
+                        status,
+                        paths.join(", ")
+                    ),
+                );
+                if let [path] = paths[..] {
+                    finding = finding.at(path, None);
+                }
+                findings.push(finding);
+            }
+
+            if linter.fix {
+                for file in &matched {
+                    findings.extend(tree.restage_if_changed(file, &linter.name)?);
+                }
+            }
+        }
+
+        Ok(findings)
+    }
+}
+
+impl StagedTree {
+    /// Write the staged contents of every file in the index (with Git's smudge filters and line ending conversion
+    /// applied, as they would be in a checkout) to a new temporary directory.
+    ///
+    /// `git checkout-index --all --prefix=DIR/`
+    fn checkout() -> Result<StagedTree> {
+        let dir = tempfile::Builder::new()
+            .prefix("git-util-staged-")
+            .tempdir()
+            .context("Failed to create a temporary directory")?;
+
+        // `checkout-index` only writes the files under the current directory, so run it from the top of the work tree
+        let cdup = GitCommand::new("rev-parse")
+            .with_default_args(&["--show-cdup"])
+            .read_stdout()?;
+        let prefix = format!("--prefix={}/", dir.path().display());
+
+        let output = Commands::new_command_with_args("git", &["checkout-index", "--all", &prefix])
+            .current_dir(Path::new(".").join(cdup.trim()))
+            .output()
+            .context("Failed to execute 'git checkout-index' command")?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Failed to check out the index: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        debug!("checked out the index to {}", dir.path().display());
+
+        Ok(StagedTree { dir })
+    }
+
+    /// Stage the linted version of `file` if the linter changed it, returning a note saying so.
+    fn restage_if_changed(&self, file: &ChangedFile, linter: &str) -> Result<Option<Finding>> {
+        let linted_path = self.dir.path().join(&file.path);
+        let linted = fs::read(&linted_path)
+            .with_context(|| format!("Failed to read {}", linted_path.display()))?;
+        let staged = staged_contents(file)?;
+
+        if linted == staged {
+            return Ok(None);
+        }
+
+        // paths given to Git are relative to the current directory, which may not be the top of the work tree
+        let cdup = GitCommand::new("rev-parse")
+            .with_default_args(&["--show-cdup"])
+            .read_stdout()?;
+        let work_tree_path = Path::new(cdup.trim()).join(&file.path);
+        let work_tree_path_str = work_tree_path.to_string_lossy();
+
+        let blob = GitCommand::new("hash-object")
+            .with_default_args(&[
+                "-w",
+                "--path",
+                &work_tree_path_str,
+                &linted_path.to_string_lossy(),
+            ])
+            .read_stdout()?;
+
+        GitCommand::new("update-index")
+            .with_default_args(&[
+                "--cacheinfo",
+                &format!("{},{},{}", file.mode, blob.trim(), work_tree_path_str),
+            ])
+            .read_stdout()?;
+
+        let unstaged_changes = fs::read(&work_tree_path).ok().as_ref() != Some(&staged);
+        let message = if unstaged_changes {
+            format!("Linter \"{linter}\" fixed and re-staged the file; the unstaged changes in the working tree were left as they are")
+        } else {
+            fs::write(&work_tree_path, &linted)
+                .with_context(|| format!("Failed to write {}", work_tree_path.display()))?;
+            format!("Linter \"{linter}\" fixed and re-staged the file")
+        };
+
+        Ok(Some(
+            Finding::note("linter-fix", message).at(&file.path, None),
+        ))
+    }
+}
+
+/// `git cat-file --filters --path=PATH BLOB`
+fn staged_contents(file: &ChangedFile) -> Result<Vec<u8>> {
+    let output = GitCommand::new("cat-file")
+        .with_default_args(&["--filters", &format!("--path={}", file.path), &file.blob])
+        .capture()?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(anyhow!(
+            "Failed to read staged contents of {}: {}",
+            file.path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
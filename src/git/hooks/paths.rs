@@ -0,0 +1,197 @@
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+
+use super::{changed_files::ChangedFile, Finding};
+use crate::{
+    git::{config::GitUtilConfig, GitCommand},
+    glob::Glob,
+};
+
+/// Device names that Windows reserves in every directory, with or without an extension.
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters that can't be used in file names on Windows; `:` is also reserved on macOS.
+const INVALID_CHARACTERS: [char; 8] = ['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+/// Checks on the paths of newly added files; see `GitUtilConfig` for the corresponding config keys.
+#[derive(Debug, Clone)]
+pub struct PathPolicy {
+    forbidden: Vec<Glob>,
+    case_collisions: bool,
+    portable_names: bool,
+    max_path_length: Option<usize>,
+}
+
+impl PathPolicy {
+    /// Load the policy from the `git-util.pre-commit.*` config settings; every check is off by default.
+    pub fn from_config() -> Result<PathPolicy> {
+        Ok(PathPolicy {
+            forbidden: Glob::new_all(&GitUtilConfig::PreCommitForbiddenPaths.get_all()?)?,
+            case_collisions: GitUtilConfig::PreCommitCheckCaseCollisions
+                .get_bool()?
+                .unwrap_or(false),
+            portable_names: GitUtilConfig::PreCommitCheckPortablePaths
+                .get_bool()?
+                .unwrap_or(false),
+            max_path_length: GitUtilConfig::PreCommitMaxPathLength.get_usize()?,
+        })
+    }
+
+    /// `false` if none of the checks are configured, so the staged files don't need to be inspected.
+    pub fn is_enabled(&self) -> bool {
+        !self.forbidden.is_empty()
+            || self.case_collisions
+            || self.portable_names
+            || self.max_path_length.is_some()
+    }
+
+    /// Check the paths of the newly added `files`; modified files were already checked when they were added.
+    pub fn check(&self, files: &[ChangedFile]) -> Result<Vec<Finding>> {
+        let mut findings: Vec<Finding> = Vec::new();
+        let added: Vec<&ChangedFile> = files.iter().filter(|f| f.added).collect();
+
+        if added.is_empty() {
+            return Ok(findings);
+        }
+
+        let tracked = if self.case_collisions {
+            tracked_paths_by_lowercase()?
+        } else {
+            HashMap::new()
+        };
+
+        for file in added {
+            let path = &file.path;
+
+            if let Some(glob) = self.forbidden.iter().find(|g| g.is_path_match(path)) {
+                findings.push(
+                    Finding::new(
+                        "forbidden-path",
+                        format!(
+                            "Path matches forbidden pattern \"{}\" ({})",
+                            glob.as_str(),
+                            GitUtilConfig::PreCommitForbiddenPaths
+                        ),
+                    )
+                    .at(path, None),
+                );
+            }
+
+            if self.case_collisions {
+                if let Some(others) = prefixes(path).find_map(|prefix| {
+                    tracked
+                        .get(&prefix.to_lowercase())
+                        .filter(|paths| paths.len() > 1)
+                        .map(|paths| {
+                            paths
+                                .iter()
+                                .filter(|p| p.as_str() != prefix)
+                                .cloned()
+                                .collect::<Vec<String>>()
+                        })
+                }) {
+                    findings.push(
+                        Finding::new(
+                            "case-collision",
+                            format!(
+                                "Path differs only in case from {}, which can't both be checked out on Windows or macOS",
+                                others.join(", ")
+                            ),
+                        )
+                        .at(path, None),
+                    );
+                }
+            }
+
+            if self.portable_names {
+                if let Some(reason) = path.split('/').find_map(non_portable_reason) {
+                    findings.push(
+                        Finding::new("non-portable-path", format!("Path {reason}")).at(path, None),
+                    );
+                }
+            }
+
+            if let Some(max) = self.max_path_length {
+                let length = path.chars().count();
+                if length > max {
+                    findings.push(
+                        Finding::new(
+                            "long-path",
+                            format!("Path is {length} characters long; the maximum is {max}"),
+                        )
+                        .at(path, None),
+                    );
+                }
+            }
+        }
+
+        Ok(findings)
+    }
+}
+
+/// Every path in the index, and each of their parent directories, grouped by their lowercase form.
+///
+/// `git ls-files -z`
+fn tracked_paths_by_lowercase() -> Result<HashMap<String, BTreeSet<String>>> {
+    let output = GitCommand::new("ls-files")
+        .with_default_args(&["-z"])
+        .read_stdout()?;
+
+    let mut paths: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for path in output.split('\0').filter(|p| !p.is_empty()) {
+        for prefix in prefixes(path) {
+            paths
+                .entry(prefix.to_lowercase())
+                .or_default()
+                .insert(prefix.to_string());
+        }
+    }
+
+    Ok(paths)
+}
+
+/// `a`, `a/b` and `a/b/c` for `a/b/c`.
+fn prefixes(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices('/')
+        .map(|(i, _)| &path[..i])
+        .chain(std::iter::once(path))
+}
+
+/// Why the path component `name` can't be checked out on Windows or macOS, if it can't.
+fn non_portable_reason(name: &str) -> Option<String> {
+    let stem = name.split('.').next().unwrap_or(name);
+
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        return Some(format!(
+            "component \"{name}\" is a reserved device name on Windows"
+        ));
+    }
+
+    if let Some(c) = name.chars().find(|c| INVALID_CHARACTERS.contains(c)) {
+        return Some(format!(
+            "component \"{name}\" contains '{c}', which is invalid on Windows{}",
+            if c == ':' { " and macOS" } else { "" }
+        ));
+    }
+
+    if name.chars().any(char::is_control) {
+        return Some(format!(
+            "component \"{}\" contains a control character, which is invalid on Windows",
+            name.escape_debug()
+        ));
+    }
+
+    if name.ends_with(['.', ' ']) {
+        return Some(format!(
+            "component \"{name}\" ends with a dot or space, which Windows removes"
+        ));
+    }
+
+    None
+}
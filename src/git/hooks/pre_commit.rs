@@ -1,18 +1,21 @@
-use anyhow::{anyhow, Context};
+use anyhow::Result;
 use log::{debug, info};
-use regex::Regex;
-use std::{
-    env::{self, VarError},
-    fmt::Display,
-    io::{self, Write},
-};
+use std::fs;
 
-use crate::{
-    git::{
-        env_vars::{GitEnvVars, GitUtilEnvVars},
-        GitCommand, GitCommandResult, GitResult,
-    },
-    print::Print,
+use super::{
+    blobs::BlobPolicy,
+    changed_files::ChangedFile,
+    checks,
+    email_policy::{EmailPolicy, Identity},
+    linters::LinterPolicy,
+    paths::PathPolicy,
+    signing::SigningPolicy,
+    text::TextPolicy,
+    Finding,
+};
+use crate::git::{
+    diff::{self, FilePatch},
+    Git, GitCommand, GitResult,
 };
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -21,90 +24,220 @@ pub struct PreCommitHook {}
 impl PreCommitHook {
     /// Run the pre-commit hook.
     ///
-    /// 1. Commit email is checked against the env value `$GIT_UTIL_USER_EMAIL`.
-    ///     - Fails if the commit email does not match.
-    /// 2. If env value `$GIT_UTIL_DISALLOWED_STRINGS` is set, the diff changes are checked for matches to the disallowed strings.
+    /// 1. The author and committer emails are checked against the `EmailPolicy` for the repository (the matching
+    ///    `git-util.email-rule`, else the env value `$GIT_UTIL_USER_EMAIL`); skipped if there is no policy.
+    ///     - Fails if either email is not allowed.
+    /// 2. If `git-util.signing.required` is set, Git must be configured to sign the commit.
+    ///     - Fails if `commit.gpgsign` is off, or `gpg.format` isn't the required `git-util.signing.format`.
+    /// 3. Each `git-util.linter` is run against the staged versions of the files matching its globs; linters that fix
+    ///    files have their changes re-staged, so the remaining checks see the fixed versions.
+    ///     - Fails if any linter exits with an error.
+    /// 4. If env value `$GIT_UTIL_DISALLOWED_STRINGS` is set, the diff changes are checked for matches to the disallowed strings.
     ///     - Fails if any added changes in the diff contain a match for any of the disallowed strings.
+    /// 5. The added lines are checked for conflict markers, trailing whitespace, a missing final newline, CRLF line
+    ///    endings in LF files and a space before a tab in the indentation; each check can be turned off.
+    ///     - Fails if any added line fails an enabled `TextPolicy` check.
+    /// 6. If any of the `BlobPolicy` checks are configured, the staged files are checked for size, binary content and
+    ///    newly added archives/executables.
+    ///     - Fails if any staged file violates the policy.
+    /// 7. If any of the `PathPolicy` checks are configured, newly added paths are checked against
+    ///    `git-util.pre-commit.forbidden-path`, for case-insensitive collisions with tracked paths, for names that are
+    ///    invalid on Windows or macOS and against the maximum path length.
+    ///     - Fails if any added path violates an enabled `PathPolicy` check.
+    ///
+    /// The changes being committed are found by comparing the index against the correct base(s): the empty tree for the
+    /// first commit on an unborn branch, and every parent for a merge, where only the changes relative to all of the
+    /// parents (e.g. conflict resolutions) are checked, since the rest was checked when it was committed on the merged
+    /// branches. For `--amend`, the changes of the amended commit itself are checked later, by `check_amend`.
     pub fn run() -> GitResult {
         info!("Running pre-commit hook");
 
-        match env::var(String::from(GitUtilEnvVars::UserEmail)) {
-            Ok(allowed_email) => match env::var(String::from(GitEnvVars::AuthorEmail)) {
-                Ok(commit_email) => {
-                    if commit_email != allowed_email {
-                        return Err(anyhow!(
-                            "Invalid commit email; {} value is \"{}\". Expected: \"{}\"",
-                            GitEnvVars::AuthorEmail,
-                            commit_email,
-                            allowed_email
-                        ));
-                    }
-                }
-                Err(err) => return get_env_var_error(&GitEnvVars::AuthorEmail.to_string(), &err),
-            },
-            Err(err) => return get_env_var_error(&GitUtilEnvVars::UserEmail, &err),
+        let bases = Self::diff_bases()?;
+
+        let mut findings: Vec<Finding> = Vec::new();
+
+        if let Some(policy) = EmailPolicy::for_repository()? {
+            for identity in [Identity::Author, Identity::Committer] {
+                findings.extend(policy.check(identity, &identity.current_email()?));
+            }
+        }
+
+        findings.extend(SigningPolicy::from_config()?.check_config()?);
+
+        let linter_policy = LinterPolicy::from_config()?;
+        if linter_policy.is_enabled() {
+            findings.extend(linter_policy.run(&Self::staged_files(&bases)?)?);
+        }
+
+        findings.extend(Self::check_changes(&bases)?);
+
+        super::report("pre-commit", &findings)
+    }
+
+    /// Run the checks on the changes of a commit that amends `HEAD`, relative to the parents of `HEAD` (or the empty
+    /// tree for a root commit), which the `commit-msg` hook does when the `prepare-commit-msg` hook saw `--amend`.
+    ///
+    /// Steps 4 to 7 of `run`; the linters were already run by the pre-commit hook on the files staged on top of `HEAD`.
+    pub fn check_amend() -> Result<Vec<Finding>> {
+        let parents: Vec<String> = GitCommand::new("rev-parse")
+            .with_default_args(&["HEAD^@"])
+            .read_stdout()?
+            .lines()
+            .map(str::to_string)
+            .collect();
+        debug!(
+            "amending HEAD; comparing the index against its parents {:?}",
+            parents
+        );
+
+        if parents.is_empty() {
+            Self::check_changes(&[empty_tree()?])
+        } else {
+            Self::check_changes(&parents)
         }
+    }
+
+    /// Steps 4 to 7 of `run`: the checks on the lines and files changed relative to `bases`.
+    fn check_changes(bases: &[String]) -> Result<Vec<Finding>> {
+        let mut findings: Vec<Finding> = Vec::new();
+
+        let disallowed = checks::disallowed_strings()?;
+        let text_policy = TextPolicy::from_config()?;
+
+        if disallowed.is_some() || text_policy.is_enabled() {
+            let patches = Self::staged_patches(bases)?;
+
+            if let Some(disallowed) = disallowed {
+                findings.extend(checks::check_disallowed_strings(&patches, &disallowed));
+            }
+
+            findings.extend(text_policy.check(&patches));
+        }
+
+        let blob_policy = BlobPolicy::from_config()?;
+        let path_policy = PathPolicy::from_config()?;
+
+        if blob_policy.is_enabled() || path_policy.is_enabled() {
+            let staged = Self::staged_files(bases)?;
 
-        match env::var(String::from(GitUtilEnvVars::DisallowedStrings)) {
-            Ok(disallowed_strings) => {
-                debug!(
-                    "{}=\"{}\"",
-                    GitUtilEnvVars::DisallowedStrings,
-                    disallowed_strings
-                );
-
-                // get diff for impending commit
-                let diff_changes_output: std::process::Output = GitCommand {
-                    subcommand: "diff-index",
-                    default_args: &["--patch", "--find-renames", "--cached", "HEAD"],
-                    user_args: &[],
-                }
-                .construct_git_command()
-                .output()
-                .with_context(|| "Failed to execute 'git diff-index' command")?;
-
-                if diff_changes_output.status.success() {
-                    let stdout = String::from_utf8(diff_changes_output.stdout)?;
-                    let stdout = stdout.lines();
-
-                    let re = Regex::new(format!("(?i){}", disallowed_strings.as_str()).as_str())
-                        .unwrap();
-
-                    debug!("{:#?}", re);
-
-                    // filter down to code additions only
-                    for line in stdout.filter(|line| line.starts_with('+')) {
-                        if re.is_match(line) {
-                            Print::stderr_purple(&format!("Disallowed addition:\n\n{line}"));
-
-                            return Err(anyhow!("Disallowed string found in commit changes!"));
-                        }
-                    }
-                    debug!("No disallowed changes found");
-                } else {
-                    io::stdout().write_all(&diff_changes_output.stdout)?
-                }
-
-                io::stderr().write_all(&diff_changes_output.stderr)?;
+            if blob_policy.is_enabled() {
+                findings.extend(blob_policy.check(&staged));
             }
-            Err(err) => {
-                if err.to_string() == "environment variable not found" {
-                    debug!(
-                        "{} not found; skipping check",
-                        GitUtilEnvVars::DisallowedStrings
-                    )
-                } else {
-                    // env var exists, but there's some other problem with it
-                    return get_env_var_error(&GitUtilEnvVars::DisallowedStrings, &err);
-                }
+            if path_policy.is_enabled() {
+                findings.extend(path_policy.check(&staged)?);
             }
         }
 
-        Ok(GitCommandResult::Success)
+        Ok(findings)
+    }
+
+    /// The trees the index is compared against to find the changes being committed.
+    ///
+    /// - A merge (`MERGE_HEAD` exists): `HEAD` and each commit being merged.
+    /// - An unborn branch: the empty tree.
+    /// - Otherwise: `HEAD`. Git doesn't tell the pre-commit hook about `--amend`, so only the changes staged on top of
+    ///   the amended commit are checked here; the rest are checked by the `commit-msg` hook (see `check_amend`).
+    fn diff_bases() -> Result<Vec<String>> {
+        if Git::rev_parse("HEAD")?.is_none() {
+            debug!("HEAD is unborn; comparing the index against the empty tree");
+            return Ok(vec![empty_tree()?]);
+        }
+
+        let merge_heads = Self::merge_heads()?;
+        if !merge_heads.is_empty() {
+            debug!(
+                "merging {:?}; comparing the index against every parent",
+                merge_heads
+            );
+            return Ok(std::iter::once("HEAD".to_string())
+                .chain(merge_heads)
+                .collect());
+        }
+
+        Ok(vec!["HEAD".to_string()])
+    }
+
+    /// The commits listed in `MERGE_HEAD`, which has one line per commit being merged.
+    fn merge_heads() -> Result<Vec<String>> {
+        let path = GitCommand::new("rev-parse")
+            .with_default_args(&["--git-path", "MERGE_HEAD"])
+            .read_stdout()?;
+
+        match fs::read_to_string(path.trim()) {
+            Ok(contents) => Ok(contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// The lines added relative to every one of `bases`.
+    ///
+    /// The diffs all describe the same (staged) version of each file, so an added line is identified by its path and
+    /// line number.
+    fn staged_patches(bases: &[String]) -> Result<Vec<FilePatch>> {
+        let mut patches: Option<Vec<FilePatch>> = None;
+
+        for base in bases {
+            // get diff for impending commit
+            let patch = GitCommand::new("diff-index")
+                .with_default_args(&["--patch", "--find-renames", "--cached", base])
+                .read_stdout()?;
+            let parsed = diff::parse_patch(&patch);
+
+            patches = Some(match patches {
+                None => parsed,
+                Some(patches) => patches
+                    .into_iter()
+                    .filter_map(|mut file| {
+                        let other = parsed.iter().find(|p| p.path == file.path)?;
+                        file.new_file &= other.new_file;
+                        file.added_lines.retain(|line| {
+                            other.added_lines.iter().any(|l| l.number == line.number)
+                        });
+                        Some(file)
+                    })
+                    .collect(),
+            });
+        }
+
+        Ok(patches.unwrap_or_default())
+    }
+
+    /// The files that are added or modified relative to every one of `bases`.
+    fn staged_files(bases: &[String]) -> Result<Vec<ChangedFile>> {
+        let mut files: Option<Vec<ChangedFile>> = None;
+
+        for base in bases {
+            let staged = ChangedFile::staged(base)?;
+
+            files = Some(match files {
+                None => staged,
+                Some(files) => files
+                    .into_iter()
+                    .filter_map(|mut file| {
+                        let other = staged.iter().find(|f| f.path == file.path)?;
+                        file.added &= other.added;
+                        Some(file)
+                    })
+                    .collect(),
+            });
+        }
+
+        Ok(files.unwrap_or_default())
     }
 }
 
-/// Add detail to `&VarError` returned from `std::env::var` call.
-fn get_env_var_error<T: Display>(env_var: &T, err: &VarError) -> GitResult {
-    Err(anyhow!("failed to get env variable {}: {}", env_var, err))
+/// The object name of the empty tree, which depends on the repository's hash algorithm.
+///
+/// `git hash-object -t tree --stdin < /dev/null`
+fn empty_tree() -> Result<String> {
+    let output = GitCommand::new("hash-object")
+        .with_default_args(&["-t", "tree", "--stdin"])
+        .capture_with_stdin(b"")?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
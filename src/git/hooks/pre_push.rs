@@ -0,0 +1,152 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use regex::Regex;
+use std::{
+    io::{self, BufRead},
+    sync::OnceLock,
+};
+
+use super::{commit_checks::CommitChecks, Finding};
+use crate::{
+    git::{config::GitUtilConfig, GitCommand, GitResult},
+    glob::Glob,
+};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct PrePushHook {}
+
+/// A line Git writes to the pre-push hook's stdin: `<local ref> <local sha> <remote ref> <remote sha>`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct RefUpdate {
+    local_ref: String,
+    local_sha: String,
+    remote_ref: String,
+    remote_sha: String,
+}
+
+impl PrePushHook {
+    /// Run the pre-push hook for a push to `remote`; the ref updates are read from stdin.
+    ///
+    /// 1. Pushes to (or deletions of) branches matching `git-util.protected-branch` fail.
+    /// 2. Every outgoing commit is checked:
+    ///     - The author and committer emails are checked against the `EmailPolicy` for the repository.
+    ///     - If env value `$GIT_UTIL_DISALLOWED_STRINGS` is set, the changes are checked for disallowed strings.
+    ///     - If any of the `BlobPolicy` checks are configured, the changed files are checked.
+    ///     - `fixup!`/`squash!`/`amend!` and WIP commits fail.
+    pub fn run(remote: &str, url: Option<&str>) -> GitResult {
+        info!("Running pre-push hook for {} ({:?})", remote, url);
+
+        let mut updates: Vec<RefUpdate> = Vec::new();
+        for line in io::stdin().lock().lines() {
+            let line = line.with_context(|| "Failed to read ref updates from stdin")?;
+            if !line.trim().is_empty() {
+                updates.push(RefUpdate::parse(&line)?);
+            }
+        }
+        debug!("ref updates: {:#?}", updates);
+
+        let protected_branches = Glob::new_all(&GitUtilConfig::ProtectedBranches.get_all()?)?;
+
+        let mut findings: Vec<Finding> = Vec::new();
+        let mut revisions: Vec<String> = Vec::new();
+        let mut exclude_remote = false;
+
+        for update in &updates {
+            if let Some(branch) = update.remote_ref.strip_prefix("refs/heads/") {
+                if protected_branches.iter().any(|g| g.is_match(branch)) {
+                    findings.push(Finding::new(
+                        "pre-push/protected-branch",
+                        format!(
+                            "Direct pushes to protected branch \"{}\" are not allowed (pushing {})",
+                            branch, update.local_ref
+                        ),
+                    ));
+                }
+            }
+
+            if is_zero_oid(&update.local_sha) {
+                // the remote ref is being deleted
+                continue;
+            }
+
+            revisions.push(update.local_sha.clone());
+
+            // commits the remote already has don't need to be checked, so every update can share one range
+            if is_zero_oid(&update.remote_sha) || !object_exists(&update.remote_sha)? {
+                exclude_remote = true;
+            } else {
+                revisions.push(format!("^{}", update.remote_sha));
+            }
+        }
+
+        if !revisions.is_empty() {
+            if exclude_remote {
+                revisions.push("--not".to_string());
+                revisions.push(format!("--remotes={remote}"));
+            }
+
+            let (count, commit_findings) =
+                CommitChecks::from_config()?.check_commits(&revisions, |commit| {
+                    check_unfinished(&commit.subject)
+                        .map(|finding| finding.in_commit(commit.short_hash()))
+                        .into_iter()
+                        .collect()
+                })?;
+
+            info!("checked {} outgoing commit(s)", count);
+            findings.extend(commit_findings);
+        }
+
+        super::report("pre-push", &findings)
+    }
+}
+
+impl RefUpdate {
+    fn parse(line: &str) -> Result<RefUpdate> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        match parts[..] {
+            [local_ref, local_sha, remote_ref, remote_sha] => Ok(RefUpdate {
+                local_ref: local_ref.to_string(),
+                local_sha: local_sha.to_string(),
+                remote_ref: remote_ref.to_string(),
+                remote_sha: remote_sha.to_string(),
+            }),
+            _ => Err(anyhow!("Invalid pre-push ref update line: \"{line}\"")),
+        }
+    }
+}
+
+/// Fail autosquash (`fixup!`, `squash!`, `amend!`) and work-in-progress commits, which shouldn't be published.
+fn check_unfinished(subject: &str) -> Option<Finding> {
+    static WIP: OnceLock<Regex> = OnceLock::new();
+    let wip = WIP.get_or_init(|| Regex::new(r"(?i)\bwip\b").unwrap());
+
+    if ["fixup! ", "squash! ", "amend! "]
+        .iter()
+        .any(|prefix| subject.starts_with(prefix))
+        || wip.is_match(subject)
+    {
+        Some(Finding::new(
+            "pre-push/unfinished-commit",
+            format!("Unfinished commit \"{subject}\" must be squashed or reworded before pushing"),
+        ))
+    } else {
+        None
+    }
+}
+
+/// `git cat-file -e OBJECT`
+fn object_exists(object: &str) -> Result<bool> {
+    Ok(GitCommand::new("cat-file")
+        .with_default_args(&["-e", object])
+        .capture()?
+        .status
+        .success())
+}
+
+/// Whether `sha` is the all-zero object name Git uses for a ref that does not exist, e.g. the remote side of a new
+/// branch; it is 40 or 64 characters long, depending on the repository's hash algorithm.
+fn is_zero_oid(sha: &str) -> bool {
+    sha.chars().all(|c| c == '0')
+}
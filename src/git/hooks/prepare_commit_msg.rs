@@ -0,0 +1,229 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use regex::Regex;
+use std::{fs, path::Path};
+
+use super::handoff::Handoff;
+use crate::git::{
+    config::{GitUtilConfig, DEFAULT_TICKET_PATTERN},
+    message::{self, CommitMessage},
+    Git, GitResult,
+};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct PrepareCommitMsgHook {}
+
+/// Where to insert the ticket ID extracted from the branch name.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum TicketPlacement {
+    None,
+    Prefix,
+    Trailer,
+}
+
+impl PrepareCommitMsgHook {
+    /// Run the prepare-commit-msg hook against the message in `file`.
+    ///
+    /// `source` and `sha` are the optional arguments Git passes to the hook. Messages from a merge, a squash or an
+    /// existing commit (`--amend`, `-c`, `-C`) are left untouched. Otherwise:
+    ///
+    /// 1. A ticket ID is extracted from the branch name using the configured patterns and either prefixed to the
+    ///    subject or added as a trailer.
+    /// 2. The configured `Co-authored-by` trailers are added.
+    ///
+    /// If the message was empty (e.g. a plain `git commit`), what was filled in is handed off to the `commit-msg` hook,
+    /// which aborts the commit if nothing else is written in the editor, as Git does for an empty message.
+    /// For `--amend`, a marker is handed off instead, so `commit-msg` can check the changes of the whole amended commit.
+    pub fn run(file: &Path, source: Option<&str>, sha: Option<&str>) -> GitResult {
+        info!(
+            "Running prepare-commit-msg hook on {} (source: {:?}, sha: {:?})",
+            file.display(),
+            source,
+            sha
+        );
+
+        Handoff::clear_all()?;
+
+        // `--amend` (without `-m` or `-F`) reuses the message of `HEAD`; so does `-c HEAD` or `-C HEAD`, which is rare
+        if source == Some("commit") && sha == Some("HEAD") {
+            Handoff::Amend.write("HEAD")?;
+        }
+
+        if let Some(source @ ("merge" | "squash" | "commit")) = source {
+            debug!("message source is \"{source}\"; leaving message untouched");
+            return super::report("prepare-commit-msg", &[]);
+        }
+
+        let mut trailers: Vec<String> = Vec::new();
+        let mut prefix: Option<String> = None;
+
+        if let Some(ticket) = Self::ticket_from_branch()? {
+            debug!("found ticket ID \"{ticket}\" in branch name");
+
+            match Self::ticket_placement()? {
+                TicketPlacement::None => (),
+                TicketPlacement::Prefix => {
+                    let format = GitUtilConfig::PrepareCommitMsgTicketPrefix
+                        .get()?
+                        .unwrap_or_else(|| "{ticket}: ".to_string());
+                    prefix = Some(format.replace("{ticket}", &ticket));
+                }
+                TicketPlacement::Trailer => {
+                    let token = GitUtilConfig::PrepareCommitMsgTicketTrailer
+                        .get()?
+                        .unwrap_or_else(|| "Refs".to_string());
+                    trailers.push(format!("{token}: {ticket}"));
+                }
+            }
+        }
+
+        for co_author in GitUtilConfig::PrepareCommitMsgCoAuthors.get_all()? {
+            trailers.push(format!("Co-authored-by: {co_author}"));
+        }
+
+        if prefix.is_none() && trailers.is_empty() {
+            return super::report("prepare-commit-msg", &[]);
+        }
+
+        let original = fs::read_to_string(file)
+            .with_context(|| format!("Failed to read commit message file {}", file.display()))?;
+
+        // a message given with `-m` or `-F` may start with the comment char, e.g. `#123: fix`; if Git adds comments
+        // (with `--edit`), they come after it
+        let comment_char = match source {
+            Some("message") => None,
+            _ => Some(message::comment_char(&original)?),
+        };
+
+        let prepared = prepare_message(&original, comment_char, prefix.as_deref(), &trailers);
+
+        if prepared != original {
+            if let Some(comment_char) = comment_char {
+                if message::strip_comments(&original, comment_char).is_empty() {
+                    Handoff::PreparedMessage
+                        .write(&message::strip_comments(&prepared, comment_char))?;
+                }
+            }
+
+            fs::write(file, prepared).with_context(|| {
+                format!("Failed to write commit message file {}", file.display())
+            })?;
+        }
+
+        super::report("prepare-commit-msg", &[])
+    }
+
+    /// Extract a ticket ID from the current branch name using the first matching ticket pattern.
+    ///
+    /// If a pattern has a capture group, the first group is used as the ticket ID; otherwise the whole match is.
+    fn ticket_from_branch() -> Result<Option<String>> {
+        let Some(branch) = Git::current_branch()? else {
+            return Ok(None);
+        };
+
+        let mut patterns = GitUtilConfig::PrepareCommitMsgTicketPatterns.get_all()?;
+        if patterns.is_empty() {
+            patterns.push(DEFAULT_TICKET_PATTERN.to_string());
+        }
+
+        for pattern in patterns {
+            let re = Regex::new(&pattern).with_context(|| {
+                format!(
+                    "Invalid {} \"{}\"",
+                    GitUtilConfig::PrepareCommitMsgTicketPatterns,
+                    pattern
+                )
+            })?;
+
+            if let Some(captures) = re.captures(&branch) {
+                let ticket = captures.get(1).or(captures.get(0)).unwrap();
+                return Ok(Some(ticket.as_str().to_string()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn ticket_placement() -> Result<TicketPlacement> {
+        match GitUtilConfig::PrepareCommitMsgTicketPlacement
+            .get()?
+            .as_deref()
+        {
+            None | Some("trailer") => Ok(TicketPlacement::Trailer),
+            Some("prefix") => Ok(TicketPlacement::Prefix),
+            Some("none") => Ok(TicketPlacement::None),
+            Some(other) => Err(anyhow!(
+                "Invalid {} \"{}\"; expected one of: trailer, prefix, none",
+                GitUtilConfig::PrepareCommitMsgTicketPlacement,
+                other
+            )),
+        }
+    }
+}
+
+/// Add `prefix` to the subject and append `trailers` to `original`, skipping any that are already present; if the
+/// message is empty, the subject line is the prefix (or left blank) for the user to fill in.
+///
+/// The comment block Git appends to the message, which starts at the first line beginning with `comment_char`, is kept
+/// after the message; with no `comment_char`, every line is part of the message.
+fn prepare_message(
+    original: &str,
+    comment_char: Option<char>,
+    prefix: Option<&str>,
+    trailers: &[String],
+) -> String {
+    let lines: Vec<&str> = original.lines().collect();
+
+    // Git puts its comments after the message
+    let comments_start = lines
+        .iter()
+        .position(|l| comment_char.is_some_and(|c| l.starts_with(c)))
+        .unwrap_or(lines.len());
+
+    let mut content: Vec<String> = lines[..comments_start]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+    while content.last().is_some_and(|l| l.trim().is_empty()) {
+        content.pop();
+    }
+
+    if content.is_empty() {
+        content.push(String::new());
+    }
+
+    if let Some(prefix) = prefix {
+        if let Some(subject) = content.first_mut().filter(|s| !s.contains(prefix.trim())) {
+            subject.insert_str(0, prefix);
+        }
+    }
+
+    let existing = CommitMessage::parse(&content.join("\n"));
+    let missing: Vec<&String> = trailers
+        .iter()
+        .filter(|trailer| {
+            !existing
+                .trailers
+                .iter()
+                .any(|t| format!("{}: {}", t.token, t.value).eq_ignore_ascii_case(trailer))
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        if existing.trailers.is_empty() || content.len() == 1 {
+            content.push(String::new());
+        }
+        content.extend(missing.into_iter().cloned());
+    }
+
+    let mut prepared = content.join("\n");
+    prepared.push('\n');
+
+    if comments_start < lines.len() {
+        prepared.push('\n');
+        prepared.push_str(&lines[comments_start..].join("\n"));
+        prepared.push('\n');
+    }
+
+    prepared
+}
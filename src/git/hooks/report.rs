@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{fs, path::PathBuf, sync::OnceLock};
+
+use super::{Finding, Severity};
+use crate::json::Json;
+
+/// The file requested with `--report FORMAT PATH`, if any.
+static REPORT: OnceLock<ReportTarget> = OnceLock::new();
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The machine-readable formats a hook can write its findings in.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ReportFormat {
+    /// `{"hook": ..., "findings": [...]}`
+    Json,
+    /// SARIF 2.1.0, for code scanning tools and CI annotations.
+    Sarif,
+}
+
+/// Where to write the report, and in which format.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReportTarget {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+impl ReportTarget {
+    /// Parse the `FORMAT PATH` values of `--report`.
+    pub fn parse(values: &[String]) -> Result<ReportTarget> {
+        let [format, path] = values else {
+            return Err(anyhow!("--report takes a format and a path"));
+        };
+
+        let format = match format.to_ascii_lowercase().as_str() {
+            "json" => ReportFormat::Json,
+            "sarif" => ReportFormat::Sarif,
+            _ => {
+                return Err(anyhow!(
+                    "Unknown report format \"{format}\"; expected \"json\" or \"sarif\""
+                ))
+            }
+        };
+
+        Ok(ReportTarget {
+            format,
+            path: PathBuf::from(path),
+        })
+    }
+
+    /// Write the findings of every hook run by this process to `self`; only one can be set.
+    pub fn set(self) -> Result<()> {
+        REPORT
+            .set(self)
+            .map_err(|_| anyhow!("A report file has already been set"))
+    }
+}
+
+/// Write `findings` to the `--report` file, if one was requested; written even if there are no findings, so that CI
+/// can tell a clean run from one that didn't happen.
+pub fn write(hook: &str, findings: &[Finding]) -> Result<()> {
+    let Some(target) = REPORT.get() else {
+        return Ok(());
+    };
+
+    let report = match target.format {
+        ReportFormat::Json => json(hook, findings),
+        ReportFormat::Sarif => sarif(findings),
+    };
+
+    debug!(
+        "writing {:?} report to {}",
+        target.format,
+        target.path.display()
+    );
+
+    fs::write(&target.path, format!("{report}\n"))
+        .with_context(|| format!("Failed to write report to {}", target.path.display()))
+}
+
+fn json(hook: &str, findings: &[Finding]) -> Json {
+    Json::object([
+        ("hook", hook.into()),
+        (
+            "findings",
+            Json::Array(
+                findings
+                    .iter()
+                    .map(|f| {
+                        Json::object([
+                            ("rule", f.rule.into()),
+                            ("severity", f.severity.to_string().into()),
+                            ("commit", f.commit.clone().into()),
+                            ("file", f.file.clone().into()),
+                            ("line", f.line.into()),
+                            ("column", f.column.into()),
+                            ("message", f.message.clone().into()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+fn sarif(findings: &[Finding]) -> Json {
+    let mut rules: Vec<&str> = findings.iter().map(|f| f.rule).collect();
+    rules.sort_unstable();
+    rules.dedup();
+
+    let results: Vec<Json> = findings
+        .iter()
+        .map(|f| {
+            let locations = f.file.as_ref().map(|file| {
+                let region = f.line.map(|line| {
+                    Json::object([("startLine", line.into()), ("startColumn", f.column.into())])
+                });
+
+                Json::Array(vec![Json::object([(
+                    "physicalLocation",
+                    Json::object([
+                        (
+                            "artifactLocation",
+                            Json::object([("uri", file.as_str().into())]),
+                        ),
+                        ("region", region.unwrap_or(Json::Null)),
+                    ]),
+                )])])
+            });
+
+            Json::object([
+                ("ruleId", f.rule.into()),
+                (
+                    "level",
+                    match f.severity {
+                        Severity::Error => "error",
+                        Severity::Note => "note",
+                    }
+                    .into(),
+                ),
+                (
+                    "message",
+                    Json::object([("text", f.message.clone().into())]),
+                ),
+                ("locations", locations.unwrap_or(Json::Null)),
+                (
+                    "properties",
+                    match &f.commit {
+                        Some(commit) => Json::object([("commit", commit.as_str().into())]),
+                        None => Json::Null,
+                    },
+                ),
+            ])
+        })
+        .collect();
+
+    Json::object([
+        ("$schema", SARIF_SCHEMA.into()),
+        ("version", "2.1.0".into()),
+        (
+            "runs",
+            Json::Array(vec![Json::object([
+                (
+                    "tool",
+                    Json::object([(
+                        "driver",
+                        Json::object([
+                            ("name", env!("CARGO_PKG_NAME").into()),
+                            ("version", env!("CARGO_PKG_VERSION").into()),
+                            (
+                                "rules",
+                                Json::Array(
+                                    rules
+                                        .into_iter()
+                                        .map(|rule| Json::object([("id", rule.into())]))
+                                        .collect(),
+                                ),
+                            ),
+                        ]),
+                    )]),
+                ),
+                ("results", Json::Array(results)),
+            ])]),
+        ),
+    ])
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use log::debug;
+
+use super::Finding;
+use crate::git::{config, config::GitUtilConfig, GitCommand};
+
+/// The `gpg.format` Git uses when it isn't set.
+const DEFAULT_SIGNING_FORMAT: &str = "openpgp";
+
+/// Requirements on commit signatures; see `GitUtilConfig` for the corresponding config keys.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SigningPolicy {
+    required: bool,
+    /// The required `gpg.format`, e.g. `ssh`; any format is accepted if `None`.
+    format: Option<String>,
+}
+
+impl SigningPolicy {
+    /// Load the policy from the `git-util.signing.*` config settings.
+    pub fn from_config() -> Result<SigningPolicy> {
+        Ok(SigningPolicy {
+            required: GitUtilConfig::SigningRequired.get_bool()?.unwrap_or(false),
+            format: GitUtilConfig::SigningFormat.get()?,
+        })
+    }
+
+    /// Check that Git is configured to sign new commits: `commit.gpgsign` is on, `gpg.format` is the required format
+    /// (if any), and a signing key is set for SSH signing, which has no default key.
+    pub fn check_config(&self) -> Result<Vec<Finding>> {
+        let mut findings: Vec<Finding> = Vec::new();
+
+        if !self.required {
+            return Ok(findings);
+        }
+
+        let gpgsign = config::get_all("commit.gpgsign", Some("bool"))?.pop();
+        if gpgsign.as_deref() != Some("true") {
+            findings.push(Finding::new(
+                "signing-config",
+                format!(
+                    "Commits must be signed ({} is set), but commit.gpgsign is not true",
+                    GitUtilConfig::SigningRequired
+                ),
+            ));
+        }
+
+        let format = config::get_all("gpg.format", None)?
+            .pop()
+            .unwrap_or_else(|| DEFAULT_SIGNING_FORMAT.to_string());
+        debug!("gpg.format={format}");
+
+        if let Some(required) = &self.format {
+            if !format.eq_ignore_ascii_case(required) {
+                findings.push(Finding::new(
+                    "signing-config",
+                    format!(
+                        "gpg.format is \"{}\", but {} requires \"{}\"",
+                        format,
+                        GitUtilConfig::SigningFormat,
+                        required
+                    ),
+                ));
+            }
+        }
+
+        if format == "ssh" && config::get_all("user.signingkey", None)?.is_empty() {
+            findings.push(Finding::new(
+                "signing-config",
+                "gpg.format is \"ssh\", but user.signingkey is not set".to_string(),
+            ));
+        }
+
+        Ok(findings)
+    }
+
+    /// Verify the signature on `commit` with `git verify-commit`.
+    pub fn check_commit(&self, commit: &str) -> Result<Option<Finding>> {
+        if !self.required {
+            return Ok(None);
+        }
+
+        let output = GitCommand::new("verify-commit")
+            .with_default_args(&[commit])
+            .capture()?;
+
+        if output.status.success() {
+            return Ok(None);
+        }
+
+        // verify-commit prints nothing for an unsigned commit, and GPG's diagnostics for a bad signature
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty())
+            .unwrap_or("the commit is not signed");
+
+        Ok(Some(Finding::new(
+            "commit-signature",
+            format!("Commit does not have a valid signature: {reason}"),
+        )))
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+
+use super::Finding;
+use crate::git::{config::GitUtilConfig, diff::FilePatch};
+
+/// The markers Git writes around a conflicted hunk; `=======` is only reported alongside one of these, since it is
+/// also used to underline Markdown and reStructuredText headings.
+const CONFLICT_MARKERS: [&str; 3] = ["<<<<<<<", "|||||||", ">>>>>>>"];
+
+const CONFLICT_SEPARATOR: &str = "=======";
+
+/// Checks on the lines added to text files; see `GitUtilConfig` for the corresponding config keys.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TextPolicy {
+    conflict_markers: bool,
+    trailing_whitespace: bool,
+    final_newline: bool,
+    line_endings: bool,
+    mixed_indentation: bool,
+}
+
+impl TextPolicy {
+    /// Load the policy from the `git-util.pre-commit.check-*` config settings; every check is off by default.
+    pub fn from_config() -> Result<TextPolicy> {
+        Ok(TextPolicy {
+            conflict_markers: GitUtilConfig::PreCommitCheckConflictMarkers
+                .get_bool()?
+                .unwrap_or(false),
+            trailing_whitespace: GitUtilConfig::PreCommitCheckTrailingWhitespace
+                .get_bool()?
+                .unwrap_or(false),
+            final_newline: GitUtilConfig::PreCommitCheckFinalNewline
+                .get_bool()?
+                .unwrap_or(false),
+            line_endings: GitUtilConfig::PreCommitCheckLineEndings
+                .get_bool()?
+                .unwrap_or(false),
+            mixed_indentation: GitUtilConfig::PreCommitCheckMixedIndentation
+                .get_bool()?
+                .unwrap_or(false),
+        })
+    }
+
+    /// `false` if every check has been turned off, so the patch doesn't need to be read.
+    pub fn is_enabled(&self) -> bool {
+        self.conflict_markers
+            || self.trailing_whitespace
+            || self.final_newline
+            || self.line_endings
+            || self.mixed_indentation
+    }
+
+    /// Check the added lines in `patches`.
+    pub fn check(&self, patches: &[FilePatch]) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = Vec::new();
+
+        for patch in patches.iter().filter(|p| !p.binary) {
+            let has_conflict_markers = patch
+                .added_lines
+                .iter()
+                .any(|l| conflict_marker(&l.text).is_some());
+
+            // a new file has no existing lines, so it only fails if its own line endings are mixed
+            let lf_file = if patch.existing_lf || patch.existing_crlf {
+                patch.existing_lf && !patch.existing_crlf
+            } else {
+                patch.added_lines.iter().any(|l| !l.crlf && !l.no_newline)
+            };
+
+            for line in &patch.added_lines {
+                let mut found = |rule: &'static str, column: usize, message: String| {
+                    findings.push(
+                        Finding::new(rule, message)
+                            .at(&patch.path, Some(line.number))
+                            .at_column(column),
+                    );
+                };
+
+                if self.conflict_markers {
+                    if let Some(marker) = conflict_marker(&line.text) {
+                        found(
+                            "conflict-marker",
+                            1,
+                            format!("Leftover merge conflict marker \"{marker}\""),
+                        );
+                    } else if has_conflict_markers && line.text == CONFLICT_SEPARATOR {
+                        found(
+                            "conflict-marker",
+                            1,
+                            format!("Leftover merge conflict marker \"{CONFLICT_SEPARATOR}\""),
+                        );
+                    }
+                }
+
+                let end = line.text.chars().count() + 1;
+
+                if self.trailing_whitespace && line.text.ends_with([' ', '\t']) {
+                    let trimmed = line.text.trim_end_matches([' ', '\t']).chars().count();
+                    found(
+                        "trailing-whitespace",
+                        trimmed + 1,
+                        "Trailing whitespace".to_string(),
+                    );
+                }
+
+                if self.final_newline && line.no_newline {
+                    found(
+                        "final-newline",
+                        end,
+                        "No newline at end of file".to_string(),
+                    );
+                }
+
+                if self.line_endings && line.crlf && lf_file {
+                    found(
+                        "line-ending",
+                        end,
+                        "CRLF line ending in a file that uses LF".to_string(),
+                    );
+                }
+
+                if let Some(column) = self
+                    .mixed_indentation
+                    .then(|| space_before_tab(&line.text))
+                    .flatten()
+                {
+                    found(
+                        "mixed-indentation",
+                        column,
+                        "Space before tab in indentation".to_string(),
+                    );
+                }
+            }
+        }
+
+        findings
+    }
+}
+
+/// The conflict marker `text` starts with, if any; Git follows each marker with a space and a label, if anything.
+fn conflict_marker(text: &str) -> Option<&'static str> {
+    CONFLICT_MARKERS.into_iter().find(|marker| {
+        text.strip_prefix(marker)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+    })
+}
+
+/// The 1-based column of a space followed by a tab in the leading whitespace of `text`, like `git diff --check`'s
+/// `space-before-tab`.
+fn space_before_tab(text: &str) -> Option<usize> {
+    let indentation = &text[..text.len() - text.trim_start_matches([' ', '\t']).len()];
+
+    // the indentation is ASCII, so byte offsets are columns
+    indentation.find(" \t").map(|i| i + 1)
+}
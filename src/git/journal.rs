@@ -0,0 +1,344 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::atomic::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::git::{config::GitUtilConfig, Git, GitCommand, GitCommandResult, GitResult, DRY_RUN};
+
+/// Older entries are dropped once the journal has this many.
+const MAX_ENTRIES: usize = 100;
+
+/// The refs that keep the objects of the journal's snapshots from being pruned.
+pub const JOURNAL_REFS: &str = "refs/git-util/journal";
+
+/// Written in place of a field that wasn't recorded.
+const NONE: &str = "-";
+
+/// The state of the working tree in a snapshot.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Worktree {
+    /// Not recorded: the snapshot is of another branch, the branch was unborn, or
+    /// `git-util.journal.snapshot-worktree` is false.
+    NotTaken,
+    /// No changes to tracked files.
+    Clean,
+    /// The hash of a `git stash create` commit, whose tree is the working tree.
+    Stash(String),
+}
+
+/// The state of the repository before a mutating subcommand ran.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    /// The arguments git-util was run with, e.g. `undo 2`.
+    pub command: String,
+    /// The branch the snapshot is of, or `None` if `HEAD` was detached.
+    pub branch: Option<String>,
+    /// The commit the branch (or `HEAD`) pointed to, or `None` if it was unborn.
+    pub head: Option<String>,
+    /// The tree written from the index, or `None` if it wasn't recorded (or had unmerged entries).
+    pub index: Option<String>,
+    pub worktree: Worktree,
+}
+
+impl Snapshot {
+    /// Snapshot `HEAD`, the current branch, the index and, unless `git-util.journal.snapshot-worktree` is false, the
+    /// working tree.
+    pub fn of_head(command: &str) -> Result<Snapshot> {
+        let head = Git::rev_parse("HEAD")?;
+
+        // `git stash create` doesn't change anything; it prints nothing if there are no changes, and fails (as does
+        // `write-tree`) if the index has unmerged entries, in which case neither is recorded
+        let worktree = match &head {
+            Some(_)
+                if GitUtilConfig::JournalSnapshotWorktree
+                    .get_bool()?
+                    .unwrap_or(true) =>
+            {
+                let stash = GitCommand::new("stash")
+                    .with_default_args(&["create"])
+                    .capture()?;
+                match String::from_utf8_lossy(&stash.stdout).trim() {
+                    _ if !stash.status.success() => Worktree::NotTaken,
+                    "" => Worktree::Clean,
+                    hash => Worktree::Stash(hash.to_string()),
+                }
+            }
+            _ => Worktree::NotTaken,
+        };
+
+        let index = GitCommand::new("write-tree").capture()?;
+
+        Ok(Snapshot {
+            time: now(),
+            command: command.to_string(),
+            branch: Git::current_branch()?,
+            head,
+            index: index
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&index.stdout).trim().to_string()),
+            worktree,
+        })
+    }
+
+    /// Snapshot only `branch`, for subcommands that change a branch that isn't checked out.
+    pub fn of_branch(command: &str, branch: &str) -> Result<Snapshot> {
+        Ok(Snapshot {
+            time: now(),
+            command: command.to_string(),
+            branch: Some(branch.to_string()),
+            head: Git::rev_parse(&format!("refs/heads/{branch}"))?,
+            index: None,
+            worktree: Worktree::NotTaken,
+        })
+    }
+
+    /// Append the snapshot to the journal, dropping the oldest entries if there are more than `MAX_ENTRIES`; nothing is
+    /// recorded for a dry run.
+    ///
+    /// The snapshot's commits and trees are kept from being pruned by `git gc` by a ref to a commit that has them as
+    /// its tree and parents, which is deleted with the entry.
+    pub fn record(&self) -> Result<()> {
+        if DRY_RUN.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let path = journal_path()?;
+        debug!("recording {:?} in {}", self, path.display());
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+
+        self.anchor()?;
+
+        let mut entries = read()?;
+        if entries.len() >= MAX_ENTRIES {
+            // newest first, so the oldest are at the end
+            let dropped = entries.split_off(MAX_ENTRIES - 1);
+            entries.reverse();
+            let lines: String = entries.iter().map(Snapshot::to_line).collect();
+            fs::write(&path, lines)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+
+            // entries taken in the same second share a ref
+            for snapshot in dropped {
+                if snapshot.time != self.time && entries.iter().all(|e| e.time != snapshot.time) {
+                    GitCommand::new("update-ref")
+                        .with_default_args(&["-d", &snapshot.reference()])
+                        .capture()?;
+                }
+            }
+        }
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut journal| journal.write_all(self.to_line().as_bytes()))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Put the repository back into the state of the snapshot.
+    ///
+    /// If the snapshot's branch (or detached `HEAD`) is checked out, it is reset to the snapshot's commit, and the index
+    /// and working tree are restored if they were recorded; a branch that isn't checked out is only moved back.
+    pub fn restore(&self) -> GitResult {
+        let head = self
+            .head
+            .as_deref()
+            .ok_or_else(|| anyhow!("The branch had no commits yet when this snapshot was taken"))?;
+        let current = Git::current_branch()?;
+
+        if self.branch.is_some() && self.branch != current {
+            let branch = self.branch.as_deref().unwrap_or_default();
+            let reference = format!("refs/heads/{branch}");
+            return GitCommand::new("update-ref")
+                .with_default_args(&["-m", "git-util oops", &reference, head])
+                .run();
+        }
+
+        if self.branch.is_none() && current.is_some() {
+            return Err(anyhow!(
+                "HEAD was detached when this snapshot was taken; check out {head} first"
+            ));
+        }
+
+        // moves the branch without touching the index or working tree, which are restored next
+        if GitCommand::new("reset")
+            .with_default_args(&["--soft", head])
+            .run()?
+            == GitCommandResult::Error
+        {
+            return Ok(GitCommandResult::Error);
+        }
+
+        let head_tree = format!("{head}^{{tree}}");
+        let index = self.index.as_deref();
+        let worktree_tree = match &self.worktree {
+            Worktree::NotTaken => None,
+            Worktree::Clean => Some(head_tree.clone()),
+            Worktree::Stash(stash) => Some(format!("{stash}^{{tree}}")),
+        };
+
+        let mut steps: Vec<Vec<&str>> = Vec::new();
+
+        if let Some(tree) = &worktree_tree {
+            // starting from the snapshot's index means only the files it tracked are overwritten or removed, so files
+            // that were untracked then are left alone
+            steps.push(vec![index.unwrap_or(&head_tree)]);
+            steps.push(vec!["-u", "--reset", tree]);
+        }
+        steps.extend(index.map(|index| vec![index]));
+
+        for args in steps {
+            if GitCommand::new("read-tree")
+                .with_default_args(&args)
+                .run()?
+                == GitCommandResult::Error
+            {
+                return Ok(GitCommandResult::Error);
+            }
+        }
+
+        // `read-tree` without `-u` leaves no stat information in the index, so every file looks changed until it is
+        // refreshed, which some commands (e.g. `git stash create`) don't do themselves
+        GitCommand::new("update-index")
+            .with_default_args(&["-q", "--refresh"])
+            .run()
+    }
+
+    /// `refs/git-util/journal/<time>`
+    fn reference(&self) -> String {
+        format!("{JOURNAL_REFS}/{}", self.time)
+    }
+
+    /// Point `reference()` at a commit whose tree is the index (or `HEAD`'s tree) and whose parents are `HEAD`, the
+    /// stash commit and the commit the ref pointed to before, if another snapshot was taken in the same second.
+    fn anchor(&self) -> Result<()> {
+        let reference = self.reference();
+        let previous = Git::rev_parse(&reference)?;
+
+        let parents: Vec<&str> = [&self.head, &previous]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .chain(match &self.worktree {
+                Worktree::Stash(stash) => Some(stash.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        let tree = match (&self.index, &self.head) {
+            (Some(index), _) => index.clone(),
+            (None, Some(head)) => format!("{head}^{{tree}}"),
+            (None, None) if parents.is_empty() => return Ok(()),
+            (None, None) => GitCommand::new("mktree")
+                .capture_with_stdin(b"")
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())?,
+        };
+
+        let mut args = vec!["-m", "git-util journal", tree.as_str()];
+        for parent in &parents {
+            args.extend(["-p", parent]);
+        }
+
+        let commit = GitCommand::new("commit-tree")
+            .with_default_args(&args)
+            .read_stdout()?;
+        let commit = commit.trim();
+
+        GitCommand::new("update-ref")
+            .with_default_args(&["-m", "git-util journal", &reference, commit])
+            .read_stdout()
+            .map(|_| ())
+    }
+
+    fn to_line(&self) -> String {
+        let worktree = match &self.worktree {
+            Worktree::NotTaken => NONE,
+            Worktree::Clean => "clean",
+            Worktree::Stash(stash) => stash,
+        };
+
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            self.time,
+            self.branch.as_deref().unwrap_or(NONE),
+            self.head.as_deref().unwrap_or(NONE),
+            self.index.as_deref().unwrap_or(NONE),
+            worktree,
+            self.command.replace(['\t', '\n'], " "),
+        )
+    }
+
+    fn parse(line: &str) -> Result<Snapshot> {
+        let fields: Vec<&str> = line.splitn(6, '\t').collect();
+        let [time, branch, head, index, worktree, command] = fields[..] else {
+            return Err(anyhow!("Invalid git-util journal entry \"{line}\""));
+        };
+
+        let optional = |field: &str| (field != NONE).then(|| field.to_string());
+
+        Ok(Snapshot {
+            time: time
+                .parse()
+                .with_context(|| format!("Invalid git-util journal entry \"{line}\""))?,
+            command: command.to_string(),
+            branch: optional(branch),
+            head: optional(head),
+            index: optional(index),
+            worktree: match worktree {
+                NONE => Worktree::NotTaken,
+                "clean" => Worktree::Clean,
+                stash => Worktree::Stash(stash.to_string()),
+            },
+        })
+    }
+}
+
+/// The journal's entries, newest first.
+pub fn read() -> Result<Vec<Snapshot>> {
+    let path = journal_path()?;
+
+    let journal = match fs::read_to_string(&path) {
+        Ok(journal) => journal,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+
+    let mut entries: Vec<Snapshot> = journal
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(Snapshot::parse)
+        .collect::<Result<_>>()?;
+    entries.reverse();
+
+    Ok(entries)
+}
+
+/// `.git/git-util/journal`, or its equivalent for a linked worktree.
+fn journal_path() -> Result<PathBuf> {
+    let path = GitCommand::new("rev-parse")
+        .with_default_args(&["--git-path", "git-util/journal"])
+        .read_stdout()?;
+
+    Ok(PathBuf::from(path.trim()))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
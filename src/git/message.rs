@@ -0,0 +1,209 @@
+use anyhow::Result;
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::git::config;
+
+/// The line `git commit --verbose` inserts above the diff; everything from this line on is removed by Git.
+const SCISSORS: &str = " ------------------------ >8 ------------------------";
+
+/// A commit message, split into subject, body and trailers.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CommitMessage {
+    /// Every line of the message, with comments removed.
+    pub lines: Vec<String>,
+    pub subject: String,
+    /// The paragraphs between the subject and the trailers.
+    pub body: String,
+    pub trailers: Vec<Trailer>,
+}
+
+/// A `Token: value` line in the final paragraph of a commit message, e.g. `Refs: ABC-123`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Trailer {
+    pub token: String,
+    pub value: String,
+}
+
+/// A subject line in the form `type(scope)!: description`.
+///
+/// See <https://www.conventionalcommits.org/>
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConventionalSubject {
+    pub kind: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl CommitMessage {
+    /// Parse a commit message that has already had comments removed.
+    pub fn parse(message: &str) -> CommitMessage {
+        let lines: Vec<String> = message.lines().map(str::to_string).collect();
+        let subject = lines.first().cloned().unwrap_or_default();
+
+        // paragraphs after the subject
+        let mut paragraphs: Vec<Vec<&str>> = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+
+        for line in lines.iter().skip(1) {
+            if line.trim().is_empty() {
+                if !current.is_empty() {
+                    paragraphs.push(std::mem::take(&mut current));
+                }
+            } else {
+                current.push(line);
+            }
+        }
+        if !current.is_empty() {
+            paragraphs.push(current);
+        }
+
+        let trailers = match paragraphs.last() {
+            Some(last) => Trailer::parse_paragraph(last),
+            None => None,
+        };
+
+        if trailers.is_some() {
+            paragraphs.pop();
+        }
+
+        CommitMessage {
+            subject,
+            body: paragraphs
+                .iter()
+                .map(|p| p.join("\n"))
+                .collect::<Vec<String>>()
+                .join("\n\n"),
+            trailers: trailers.unwrap_or_default(),
+            lines,
+        }
+    }
+
+    /// The values of every trailer whose token matches `token` (case-insensitive).
+    pub fn trailer_values(&self, token: &str) -> Vec<&str> {
+        self.trailers
+            .iter()
+            .filter(|t| t.token.eq_ignore_ascii_case(token))
+            .map(|t| t.value.as_str())
+            .collect()
+    }
+
+    /// `true` if the subject was generated by Git for a merge or marks an autosquash commit.
+    pub fn is_generated(&self) -> bool {
+        ["Merge ", "Revert \"", "fixup! ", "squash! ", "amend! "]
+            .iter()
+            .any(|prefix| self.subject.starts_with(prefix))
+    }
+}
+
+impl Trailer {
+    /// Parse `paragraph` as a block of trailers; `None` if any line is not a trailer or a continuation line.
+    fn parse_paragraph(paragraph: &[&str]) -> Option<Vec<Trailer>> {
+        static TRAILER: OnceLock<Regex> = OnceLock::new();
+        let re =
+            TRAILER.get_or_init(|| Regex::new(r"^([A-Za-z0-9][A-Za-z0-9-]*): ?(.*)$").unwrap());
+
+        let mut trailers: Vec<Trailer> = Vec::new();
+
+        for line in paragraph {
+            if let Some(captures) = re.captures(line) {
+                trailers.push(Trailer {
+                    token: captures[1].to_string(),
+                    value: captures[2].trim().to_string(),
+                });
+            } else if line.starts_with(char::is_whitespace) && !trailers.is_empty() {
+                // continuation of the previous trailer's value
+                let last = trailers.last_mut().unwrap();
+                last.value.push(' ');
+                last.value.push_str(line.trim());
+            } else {
+                return None;
+            }
+        }
+
+        Some(trailers)
+    }
+}
+
+impl ConventionalSubject {
+    pub fn parse(subject: &str) -> Option<ConventionalSubject> {
+        static CONVENTIONAL: OnceLock<Regex> = OnceLock::new();
+        let re = CONVENTIONAL
+            .get_or_init(|| Regex::new(r"^([A-Za-z]+)(?:\(([^()\s]+)\))?(!)?: (\S.*)$").unwrap());
+
+        re.captures(subject).map(|captures| ConventionalSubject {
+            kind: captures[1].to_string(),
+            scope: captures.get(2).map(|m| m.as_str().to_string()),
+            breaking: captures.get(3).is_some(),
+            description: captures[4].to_string(),
+        })
+    }
+}
+
+/// The characters `core.commentChar=auto` picks from, in order.
+const AUTO_COMMENT_CHARS: [char; 10] = ['#', ';', '@', '!', '$', '%', '^', '&', '|', ':'];
+
+/// The character Git uses to mark comment lines in the commit message file `message` (`core.commentChar`; defaults to
+/// `#`).
+///
+/// With `auto`, Git uses the first of `AUTO_COMMENT_CHARS` that doesn't start a line of the message it is about to
+/// edit, and adds its comments after the message, so it is the character of the scissors line (`--verbose`), else the
+/// one that starts the last line starting with any of them.
+pub fn comment_char(message: &str) -> Result<char> {
+    let configured = config::get_all("core.commentChar", None)?.pop();
+
+    Ok(match configured.as_deref() {
+        None => '#',
+        Some("auto") => auto_comment_char(message),
+        Some(value) => value.chars().next().unwrap_or('#'),
+    })
+}
+
+/// The comment character `core.commentChar=auto` picked for `message`, or `#` if there are no comments to tell by.
+fn auto_comment_char(message: &str) -> char {
+    let candidates = message.lines().filter_map(|line| {
+        let c = line
+            .chars()
+            .next()
+            .filter(|c| AUTO_COMMENT_CHARS.contains(c))?;
+        Some((c, line[c.len_utf8()..] == *SCISSORS))
+    });
+
+    let mut last = None;
+    for (c, is_scissors) in candidates {
+        if is_scissors {
+            return c;
+        }
+        last = Some(c);
+    }
+
+    last.unwrap_or('#')
+}
+
+/// Remove comment lines and the verbose-mode diff (everything after the scissors line), as `git commit` does.
+pub fn strip_comments(message: &str, comment_char: char) -> String {
+    let scissors = format!("{comment_char}{SCISSORS}");
+    let mut stripped: Vec<&str> = Vec::new();
+
+    for line in message.lines() {
+        if line == scissors {
+            break;
+        }
+        if !line.starts_with(comment_char) {
+            stripped.push(line.trim_end());
+        }
+    }
+
+    // drop leading and trailing blank lines
+    let start = stripped
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(stripped.len());
+    let end = stripped
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |i| i + 1);
+
+    stripped[start..end].join("\n")
+}
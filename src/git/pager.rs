@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use log::debug;
+use std::{
+    env,
+    io::{self, ErrorKind, IsTerminal, Write},
+    process::{Command, Stdio},
+    sync::atomic::Ordering,
+};
+
+use crate::{
+    git::{config, NO_PAGER},
+    print::Print,
+};
+
+/// Write `output`, rendered by git-util for `subcommand`, through the pager; or straight to `stdout` if it isn't a
+/// terminal or paging is turned off (with `--no-pager`, `pager.<subcommand>` or a pager of `cat`).
+///
+/// A pager or reader that exits before reading everything, e.g. `less` quit with `q` or `| head`, is not an error.
+pub fn page(subcommand: &str, output: &[u8]) -> Result<()> {
+    let pager = if io::stdout().is_terminal() && !NO_PAGER.load(Ordering::Relaxed) {
+        pager_for(subcommand)?
+    } else {
+        None
+    };
+
+    let written = match pager {
+        Some(pager) => write_to_pager(&pager, output),
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(output).and_then(|_| stdout.flush())
+        }
+    };
+
+    match written {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        written => written.with_context(|| format!("Failed to write {subcommand} output")),
+    }
+}
+
+/// The pager for `subcommand`, as Git chooses it: `pager.<subcommand>` if it is set to a command, else `GIT_PAGER`,
+/// `core.pager`, `PAGER` or `less`; `None` if `pager.<subcommand>` is false or the pager is empty or `cat`.
+fn pager_for(subcommand: &str) -> Result<Option<String>> {
+    let configured = config::get_all(&format!("pager.{subcommand}"), None)?.pop();
+
+    let pager = match configured.as_deref().map(str::to_lowercase).as_deref() {
+        Some("false" | "no" | "off" | "0") => return Ok(None),
+        None | Some("true" | "yes" | "on" | "1" | "") => match env::var("GIT_PAGER") {
+            Ok(pager) => pager,
+            Err(_) => match config::get_all("core.pager", None)?.pop() {
+                Some(pager) => pager,
+                None => env::var("PAGER").unwrap_or_else(|_| "less".to_string()),
+            },
+        },
+        Some(_) => configured.unwrap_or_default(),
+    };
+
+    debug!("pager for {subcommand} is \"{pager}\"");
+
+    Ok(match pager.trim() {
+        "" | "cat" => None,
+        pager => Some(pager.to_string()),
+    })
+}
+
+/// Run `pager` through the shell, as Git does, and write `output` to it.
+fn write_to_pager(pager: &str, output: &[u8]) -> io::Result<()> {
+    let mut command = Command::new("sh");
+    command.args(["-c", pager]).stdin(Stdio::piped());
+
+    // the defaults Git uses: quit if everything fits on one screen, pass colors through and don't clear the screen
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    if env::var_os("LV").is_none() {
+        command.env("LV", "-c");
+    }
+
+    Print::print_command(&command);
+
+    let mut child = command.spawn()?;
+    let written = child
+        .stdin
+        .take()
+        .expect("the pager's stdin should be piped")
+        .write_all(output);
+
+    // stdin is closed by now, so the pager sees the end of the output
+    child.wait()?;
+    written
+}
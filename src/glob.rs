@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// A shell-style wildcard pattern.
+///
+/// `*` matches any sequence of characters (including `/`), `**/` matches zero or more directories (as in gitignore, so
+/// `src/**/*.rs` matches `src/main.rs`), `?` matches any single character, and `[...]` matches any one of the enclosed
+/// characters. Everything else matches literally.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Glob> {
+        let mut regex = String::from("^");
+        let mut chars = pattern.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                '[' => {
+                    let mut class = String::new();
+                    let mut closed = false;
+
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            closed = true;
+                            break;
+                        }
+                        class.push(c);
+                    }
+
+                    if closed {
+                        regex.push('[');
+                        if let Some(negated) = class.strip_prefix('!') {
+                            regex.push('^');
+                            regex.push_str(&negated.replace('\\', r"\\"));
+                        } else {
+                            regex.push_str(&class.replace('\\', r"\\"));
+                        }
+                        regex.push(']');
+                    } else {
+                        regex.push_str(&regex::escape(&format!("[{class}")));
+                    }
+                }
+                _ => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+
+        regex.push('$');
+
+        Ok(Glob {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&regex).with_context(|| format!("Invalid glob \"{pattern}\""))?,
+        })
+    }
+
+    /// Parse each of `patterns` into a `Glob`.
+    pub fn new_all(patterns: &[String]) -> Result<Vec<Glob>> {
+        patterns.iter().map(|p| Glob::new(p)).collect()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    /// Match against a repository path; patterns without a `/` are matched against the file name only.
+    pub fn is_path_match(&self, path: &str) -> bool {
+        if self.pattern.contains('/') {
+            self.is_match(path)
+        } else {
+            self.is_match(path.rsplit('/').next().unwrap_or(path))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
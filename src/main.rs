@@ -7,6 +7,8 @@ use print::Print;
 mod cli;
 mod commands;
 mod git;
+mod glob;
+mod json;
 mod print;
 
 fn main() -> ! {
@@ -1,8 +1,10 @@
 use crate::git::PRINT_COMMANDS;
 use nu_ansi_term::{AnsiString, AnsiStrings, Color};
 use std::{
-    io::{stderr, IsTerminal},
-    process::Command,
+    env,
+    fs::File,
+    io::{stderr, stdout, IsTerminal},
+    process::{Command, Stdio},
 };
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -28,6 +30,38 @@ impl Print {
         Self::stderr_color(&message, Color::Red)
     }
 
+    /// Print Warning message to `stderr`.
+    pub fn warning(message: &str) {
+        let message: String = "Warning: ".to_owned() + message;
+
+        Self::stderr_color(&message, Color::Yellow)
+    }
+
+    /// The width of the terminal `stdout` is connected to, or `None` if it isn't a terminal.
+    ///
+    /// Uses `COLUMNS` if it is set, else asks `stty`.
+    pub fn terminal_width() -> Option<usize> {
+        if !stdout().is_terminal() {
+            return None;
+        }
+
+        if let Some(columns) = env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+            return Some(columns);
+        }
+
+        // `stty size` prints "ROWS COLUMNS" for the terminal on its stdin, which `output` would otherwise replace; stdin
+        // itself may be a pipe, so the controlling terminal is used if there is one
+        let tty = File::open("/dev/tty")
+            .map(Stdio::from)
+            .unwrap_or_else(|_| Stdio::inherit());
+        let output = Command::new("stty").arg("size").stdin(tty).output().ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok()
+    }
+
     /// Print `message` in `color` to `stderr`.
     fn stderr_color(message: &str, color: Color) {
         if stderr().is_terminal() {